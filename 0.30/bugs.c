@@ -0,0 +1,4357 @@
+// Bugs - a genetic programming experiment
+// Inspired by the 1992 Scientific American article that I have not yet read
+// (c) 2003 Mike Inman - all rights reserved
+
+// sudo apt install libgd-dev
+
+// gcc bugs.c -lgd -lm
+
+// convert b*.jpg ppm:- | ppmtoy4m -S 420_mpeg2 -v 2 | mpeg2enc -o bugs022.m1v -f 3 -v 2 -b 7500 -I 0 -n n -H -M 0
+// or:
+// ffmpeg -f image2 -pattern_type glob -framerate 60 -i 'b*.jpg' -s 1920x1080s bugs029.mp4
+
+// Revision history
+//
+// 0.09 28Nov2003
+//   Initiate Revision History
+//   Add position and family history to bug dump
+//   Move tweakgene into a function, add possibility of multiple adjustments in a tweak
+//   Also add possibility of tweakgene call on a newly added gene
+//   Adjusted bug_one to reproduce more easily, increased FOODSTART and radically decreased FOODSPREAD
+//   Modified GENECOST to work on ngenes cubed divided by GENEKNEE squared
+//   Added expression filter to only evaluate one (randomly selected) chromosome per function
+//   Changed grand prize to slasher prize
+//
+// 0.10 30Nov2003
+//   Drop COSTMATE from 70 to 15
+//   Bump COSTMOVE from 37 to 38
+//   Add LEFTBAR status graph
+//   Add gene distribution within chromosomes to bug dump (text) report
+//   Revised leangenes prize to function with short lived bugs
+//
+// 0.11 04Dec2003
+//   Add a series of dynamic challenges:
+//     At year 0.5, turn off the food leak into cells containing bugs (eliminate sedentary bugs)
+//     Add dynamic geneknee2 variable that slowly increases cost of genes - objective: building a better bug_one for later versions
+//     Force mating, not for bug one, but once starting at Q1 and once per brood starting at Q3
+//   Change annual reports to quarterly
+//   Took additive age out of slasher formula
+//
+// 0.12 07Dec2003
+//   Add forcemate 3 and 4 levels for additional cost to dividing without mating (actually forces intelligent divide decisions)
+//   Rearrange forcemate escalation to take place more quickly and up through 4 now
+//   Separate seasonal foodgrowth into its own function (easing into the idea of "three continents")
+//   Revise mechanics of division so that offspring are born to the rear (reduces mating with children)
+//   Accelerated "genesqueeze" to once per 256 turns (from 512)
+//   Increased FOODSPREAD from 1 to 10, note that the leak is turned off to the bugs at 4096
+//   Revised bugone move to be less involved
+//   Add dynamic costmate to slowly increase the cost of mating
+//
+// 0.13 12Dec2003
+//   Add forcemate 5, which actually requires bug to be a certain age before dividing
+//   Incrementally ramp up the age of division to encourage longevity - with the simultaneous objective of lowering starvation rates
+//   Comment out dynamic geneknee code - removes encouragement for simple genomes
+//   Increase costmate by geometric progression instead of incrementing every 1024 turns
+//   Add selfage sense (might be useful in holding off premature division decisions)   
+//   Copied evolved gene sets from year 11.08 of Bugs 0.12 for use in bug_one
+//   Evolved genes were not spontaneously dividing in one bug environment - restored divide and turn decision to original bug_one genes
+//   Change forcemate to a binary flag decision variable instead of levels - modified progression to later turn off mating requirements
+//   Added population driven control of agediv - if population dips too low, drop agediv to make it easier
+//   Adjusted COSTMOVE 38-35 COSTTURN 10-15 COSTSLEEP 1-10
+//
+// 0.14 16Dec2003
+//   Widen blackout food growth around bugs to a distance of up to 3 cells instead of one, at one cell bugs would be born, 
+//     eat, turn, then give birth into an adjacent cell where food has grown in.  Not a terribly interesting life.
+//   Added rot[] array defining food decay rates around bugs
+//   Tweaked random seed - noted a high degree of dependance upon the random seed for outcome...
+//   Fixed MAJOR bug with move action - reduced rot distance to 1 cell - increased world size to work with new ~3% density
+//
+// 0.15 20Dec2003
+//   Change population density limit from 2% to 4%
+//   Change food leak back to single cell exclusion (from 7 cell)
+//   Added three zone cosine terrain
+//   Added ethnicity
+//   Added color-trace ethnicity plot
+//   Switched seasonal sweep to right-to-left from bottom-to-top
+//   Revamped cost structure to increase cost of movement
+//   Added colorful gene and parent tracking
+//
+// 0.16 22Dec2003
+//   Tweak population limit to 24000 from 16384
+//   Tweak ETHNIC_DUR to 32 from 40
+//   Change _ethnicity implementation to chars from shorts
+//   Put costmate on a progressive escalation
+//   Start food growth regulation based on age of division (thus, indirectly based on population)
+//
+// 0.17 05Jan2004
+//   Drop mating requirement
+//   Increase ETHNIC_DUR to 120
+//
+// 0.18 07Jan2004
+//   Restore mating requirement
+//   Implement dynamic target population - reducing over time
+//   Revise dynamic food supply to smaller changes more often (net higher mobility) and target agediv to 30-300 from 10-100
+//   Increase geneknee from 36 to 48
+//
+//   COMET STRIKE IN YEAR 75!  -  believed to be tied to an overflow condition in the growing_season function, a radical
+//     reduction in food supplies leads to mass starvation near the end of Year 75, ultimately leading to extinction in 
+//     the 0.18 batch of bugs.  Decision made to leave this condition in place, 2.5 million turns is enough.
+//
+// 0.19 15Feb2004
+//   Implement on/off mating requirement
+//   Increase report frequency
+//
+// 0.20 28Feb2004
+//   Decrease targetpop 50% to 12000
+//   Decrease genecost 67% to 128
+//   Increase geneknee 2x to 96
+//
+// 0.21 02Mar2004
+//   Increase agressiveness of AgeDiv such that AgeDiv = age of oldest bug when population exceeds POP_HARDLIMIT (25000)
+//
+// 0.22 02July2004
+//   Rerun version 21
+//
+// Revisit on new machine 12Mar2005
+//   Change include <fastmath.h> to <math.h>
+//
+// 0.23 13Mar2005
+//   Increase targetpop     from 12000 to 120000
+//   Increase POP_HARDLIMIT from 24000 to 130000
+//
+// 0.24 15Mar2005
+//   Add a FOODCAP*10 hardlimit on food levels
+//   Boost foodhump to 10.0 (from 1.4) when population first passes 1000, (for increased population density, 1.4 limits around 14,000)
+//   Add MASSCAP medium-hard limit on bug girth
+//  Change leftside genes plot to go from min to maxgenes instead of 0 to maxgenes
+//  Decrease world size to 192x160, targetpop to 5000
+//
+// 0.25 18Mar2005
+//   Change mercy limit from 1000 to WORLD_X
+//   Many tweaks to resolution, ratio, etc.
+//   This batch of bugs learns avoidance in the first year or two, and not much else for the next 100 years.
+//
+// 0.26 25Mar2005
+//   Adding water and elevation to the terrain
+//   I want more complexity in the landscape, and I think the easiest way is to have the bugs themselves carve a terrain shape that holds water
+//   the water will be a necessity of life, and will flow into the low spots carved by the bugs, and if it gets too deep they can drown in it.
+//
+// 0.27 26Mar2005
+//   Increasing size of map, decreasing density of bugs, increasing season length
+//
+// 0.28 28Mar2005
+//   Refining flow - repeat 4x for reduced viscosity
+//   Refining elevation - define maximum slope, erode any steeper slopes - erode slopes with fast flowing water
+//   Add soil spew for eating
+//   Accelerate drink_or_die reduction
+//   Lower water level required to drown, but require multiple turns underwater to expire
+//   Revise water level for drowning to a fixed depth, instead of relative to weight, adjust sense channel accordingly
+//
+// Future ideas:
+//   Aging representation in bottom graph - break white population marker into 4 zones, <10, <100, <1000, >1000 with shades for each
+//   Salmon sense - record birthplace and give sense of distance and direction relative to it
+//   Navigational sense - ability to know longitude and latitude and compass direction
+//   Switch to "wide screen" 16:9 format, maybe 960x540?
+//   Use "sign corrected" square root to flatten the food profile peaks and widen the valleys
+//   Slowly increase cost of reproduction 
+//
+// 0.29 21Jan2020
+//   Revise screen size to 1920x1080
+//   update build instructions
+//
+// 0.30 16Oct2026
+//   Add FREEZEGENES switch - no mutations, offspring are clones of the parent, no recombination either (pure selection, no drift)
+//   Add lifespan[] histogram of age at death, power of 2 bins, listed in the bug report
+//   Add OUT_X, OUT_Y output image size, plots are resampled to that size when set (thumbnails, HD video)
+//   Add set_cell() and load_scenario() - cells listed in SCENARIO are set after init_world, for authored test worlds
+//   Fix cause of death report reading the bug after kill_bug() freed it
+//   Add SCENT option - bugs leave scent on their cell each turn, it spreads and decays with the food, sensed like food
+//   Add GENEEXP - gene cost is ngenes^GENEEXP / geneknee^(GENEEXP-1), 3 reproduces the cubic cost, geneknee2 becomes geneknee
+//   Add "bugs selftest" - runs SELFTESTDAYS days checking the bug list, world links, genes and food, then twice from RNGSEED which must end alike, exits 1 on any failure
+//   Fix offspring facing - was left outside -2..3 after division, which made their first turn CW or CCW jump the wrong way
+//   Add crowding stress - a bug with more than CROWDLIMIT neighbors within CROWDRADIUS pays CROWDCOST per extra neighbor
+//   Add FOODNOISE - starting food varies by up to FOODNOISE% in smooth patches FOODNOISECELL wide, rich and poor land from day 0
+//   Add FOODJITTER - each cell's daily growth varies by a repeatable per cell, per day amount, breaks up lockstep regrowth
+//   Add mate_rejects - mating attempts turned down by the partner's RESPONSEMATE, still counted in mate_fails too
+//   Add DECPROFILE - counts how often each decision wins and its average output, listed and cleared in each bug report
+//   Add Ctrl-C handling - the day in progress is finished, then final.html and final.jpg are written before exit
+//   Add SENSERADIUS option - 6 more senses, average food in each sixth of the hex ring SENSERADIUS cells out
+//   Move the action colors to act_color(), add LEGEND switch to draw the action color key in the bottom left corner
+//   Add WALLED switch - the world edges become walls, off_map() blocks moves, mating, births, flows and senses across them
+//   Add reset_sim() - clears the bugs, dynamic challenges and history and starts over on a new seed, RESTARTS on extinction
+//   Add ethnic bands - population by dominant color (red, green, blue or mixed) kept in hist[], listed in the bug report
+//   Add EATREACH - eating may also take up to this share of the food in the cell ahead, after the bug's own cell is eaten bare
+//   Move the day's work out of main() into one_day(), add advance() and "bugs days N" to run N days with no plots, then report
+//   Add GIFOUT - plots saved as 256 color GIFs instead of JPEGs, much smaller animations of the flat color maps
+//   Add DECISIONSQUASH - each gene's term is squashed to v*S/(|v|+S), one big constant can no longer outvote the senses
+//   Add RECORDFRAMES switch for the frames and yearly reports, stats.csv of the daily history is written at the end of every run
+//   Add genome_distance() and GENOMECLUSTERS - the report groups the living bugs into species by genome, not by color
+//   Add bug_alive() - the starve, thirst and drown rules in one place, returns the cause, also checked by the selftest
+//   Add TRACKLINEAGE - the bug map shows one bug's family at full color and dims the rest, to follow a lineage as it spreads
+//   Add ENVTERRAIN - the environment map gets a gray base from the elevation, under the bugs, food and water colors, LEGEND keys its scales
+//   Add RANDOMSTART - bug one gets chromosomes of random genes instead of the hand built ones, behavior has to evolve from scratch
+//   Fix kill_bug() leaving the freed last bug at the head of the list when the population dies out
+//   Add DEBUGINDEX - check_world() after every day of any run, abort() on the day the list and the map first disagree
+//   Add EATEFFICIENCY - share of the food eaten that turns into weight, the land still loses all of it
+//   Add behavior_summary() to bug_dump() - the actions ranked by how the bug's decisions score its current senses
+//   Add FRAMEINTERVAL for the days between animation frames, was fixed at 4, longer runs can thin out the frames
+//   Add bugs_in_rect() to list the bugs in a rectangle of the map, wrapping across the edges, checked in the selftest
+//   Add PHENOMAP - p*.jpg frames color each bug by the decision it would make on its current senses, split out decision_values()
+//   Selftest checks that no chromosome has lost its first gene, bugdecide() has no empty chromosome case
+//   Add FOODHALFLIFE - the FOODCAP soft limit halves every so many days, an ageing world to force adaptation to scarcity
+//   Add rngdraws - count of limitedrandom() calls, in the bug report and selftest, a quick check that two runs drew alike
+//   Add COLORBY - the bug map can color bugs by uid, age or action instead of ethnicity, to pick individuals out of a crowd
+//   Add PARENTSHARE - how much of its weight the parent keeps when it divides, the default still splits evenly
+//   Add MASSLIMIT hard weight clamp, long long in the weight and food products of costcalc(), eating, division and the senses, safe for a 32 bit long
+//   Add POPPRESSURE - a sense of the world population against target_pop, so division timing can follow the crowding
+//   Add catastrophes[] - a schedule of famines, floods and die-offs over a rectangle of the map, for studies of recovery
+//   Add VERSION and run.txt - the version, seed, start time and outcome changing switches of the run, beside its output
+//   Add gene_canon() - normalize the fields a gene type ignores before gene_distance(), compare genes count as the match they act as, no dead gene pruning
+//   Add ACTHISTORY - how far back the time-since-action senses look, separate from the POSHISTORY trail on the bug map
+//   Add SPAWNRADIUS - offspring can be set down further out from the parent, so a family does not box itself in
+//   Add load_seed_image() - a SEEDIMAGE png paints the starting elevation, food and water, read before the SCENARIO file
+//   Add STUCKWINDOW, run_stuck() - flag a run where bugs, food, movement and births have gone flat, STUCKSTOP ends it
+//   Add DIVIDEMIN, DIVIDEMAX, DIVIDEMASS - the range divide can mutate in, and the weight each child must get
+//   Add BACKSTEP - a step back without turning as decision 7, response mate moves to 8, bug one's sense numbers now symbolic
+//   Selftest checks the bug list is in uid order, walking buglist from first is walking the bugs by uid
+//   Add ACTIONSPERDAY - bugs can take more than one turn a day, bug_move() returns 0 when the bug died
+//   Check the defines at build time - #error for settings that can't work, #warning for ones that would waste a run
+//   Pack the world cells from 48 to 32 bytes - int water, elevation and scent, short nearest, food stays long
+//   Add brain_dot() and the "dot <days>" command - the oldest bug's genes as a Graphviz graph in bug.dot
+//   Add state_hash(), vectors[] and the "verify" command - rerun known seeds and compare, to check a build reproduces them
+//   Add RANDOMFACE - bug one starts facing a direction drawn from the seed instead of always east
+//   Add IMMIGRATE - while the population is low, a few bugs with random brains arrive every so many days
+//   Add new_bug() - places a blank bug on the map and the list, used by bug_one() and immigrate()
+//   Add actcount[] to each bug - lifetime turns on each action plus times mated and defended, listed in bug_dump()
+//   Add phases[] - the order of one_day()'s steps as a table, PHASETRACE prints each one as it runs
+//   Add STATSBIN - stats.bin holds the daily history a column at a time for fast plotting, read_stats_bin() reads one back
+//   COLORID steps each bug's hue round the wheel by the golden ratio of its uid, so bugs born close together look far apart
+//   Add PROFILE - a rolling average of the time each of phases[] takes, printed under the status line
+//   Offspring are made by new_bug() too, the one place uids are handed out
+//   Add SLEEPRECOVER - sleeping several turns in a row costs less each turn, so a long sleep can be worth choosing
+//   Bug one starts with expression 0, it was never set, so a run after reset_sim() could differ from the first
+
+
+
+#include <gd.h>
+#include <gdfontt.h>
+#include <math.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+
+// Note on the linked list structures
+// Lists are maintained by a top pointer, either the buglist or chromosomes within the bug
+// Lists are double-linked, with *next == NULL on the last item and *prev == NULL on the first item
+// the empty list case is signalled by the top pointer == NULL
+
+#define VERSION      "0.30"  /* written to run.txt with the settings, to tell the output of one run from another */
+#define WORLD_X        1760
+#define WORLD_Y        1000
+#define LEFTBAR          80
+#define RIGHTBAR         80
+#define SIDEBAR          LEFTBAR + RIGHTBAR
+#define BOTTOMBAR        80
+#define WALLED            0  /* 1 = edges are walls instead of wrapping around, nothing moves, flows or is sensed across them */
+#define COLORBY           0  /* bug map colors: 0 ethnicity, 1 uid, 2 age, 3 action - see COLORETH etc. */
+#define COLORETH          0
+#define COLORID           1  /* full brightness hue stepped by the golden ratio per uid, successive bugs land far apart */
+#define COLORAGE          2
+#define COLORACT          3
+#define TRACKLINEAGE     -1  /* >= 0: bug map dims every bug not descended from the bug with this uid, -1 = off */
+#define ENVTERRAIN        0  /* >0: environment map adds up to this much gray for height, half of it for the starting level */
+#define LEGEND            0  /* 1 = label the RIGHTBAR action colors in the lower left corner of the plots, the environment map gets a key to its color scales */
+#define OUT_X             0  /* size of saved images, 0 = native size, WORLD_X + SIDEBAR by WORLD_Y + BOTTOMBAR */
+#define OUT_Y             0  /* otherwise the plot is resampled (averaged) to OUT_X by OUT_Y before saving      */
+#define RECORDFRAMES      1  /* 0 = no animation frames or yearly reports, only the final report and stats.csv          */
+#define STATSBIN          0  /* 1 = also write stats.bin, the same history as one contiguous array per column     */
+#define FRAMEINTERVAL     4  /* days between animation frames, yearly reports keep their own schedule                 */
+#define PHENOMAP          0  /* 1 = also save p*.jpg frames, each bug in the color of the action it would take, slow    */
+#define GIFOUT            0  /* 1 = save the plots as GIF, reduced to a 256 color palette, instead of JPEG      */
+#if GIFOUT
+#define IMGEXT       ".gif"
+#else
+#define IMGEXT       ".jpg"
+#endif
+#define SEASONLENGTH  16384
+#define SEEDIMAGE       "seed.png"  /* optional, paints the starting world: red elevation, green food, blue water */
+#define SCENARIO      "scenario.txt"  /* optional, one cell per line: x y food water elevation (internal units) */
+#define RNGSEED       54321   /* starting seed for limitedrandom()                                     */
+#define RESTARTS          0   /* when all bugs die, start over this many times, seed RNGSEED + restart */
+#define FOODCAP     1024000   /* cap out at x food per cell - food values recorded * 1024              */
+#define FOODHALFLIFE      0   /* >0: FOODCAP halves every x days, the land wears out, 0 = never        */
+#define FOODGROW       1044   /* food multiplies by x per turn (day)                                   */
+#define FOODJITTER        0   /* daily growth of each cell varies +/- x/1024 of itself, 0 = lockstep   */
+#define FOODSHADOW      973   /* food decays when bug is sitting on cell                               */
+#define FOODSPREAD       10   /* food spreads into poorer adjacent cells at x% per turn                */
+#define FOODSTART    128000
+#define FOODNOISE         0   /* starting food varies +/- x% of FOODSTART, 0 = uniform start          */
+#define FOODNOISECELL    64   /* size of the starting food patches, in cells                           */
+#define FOODDECAY       115   /* Rate at which overages decay                                          */
+#define COSTSLEEP        12
+#define SLEEPRECOVER      0  /* >0: each turn already asleep in a row cuts this sleep's cost by x/1024, down to COSTSLEEP/4 */
+#define COSTEAT          48
+#define COSTTURN         16
+#define COSTMOVE         96
+#define COSTFIGHT        36  /* additional cost on top of moving                                         */
+#define COSTMATE         12  
+#define COSTDIVIDE    25600  /* cost per resulting creature (ex: divide into 3, child = parent / 3 - 25) */
+#define DIVIDEMIN         2  /* fewest pieces a division can make, parent included, 2 = binary fission */
+#define DIVIDEMAX         7  /* most pieces, no more than 7 - the parent and its 6 neighbor cells */
+#define DIVIDEMASS  DIETHIN  /* a division only happens if every child gets at least this weight */
+#if ( DIVIDEMIN < 2 ) || ( DIVIDEMAX > 7 ) || ( DIVIDEMIN > DIVIDEMAX )
+#error DIVIDEMIN and DIVIDEMAX must be within 2 to 7, and in order
+#endif
+#define SPAWNRADIUS       1  /* offspring are born this many cells out from the parent, 1 = next door */
+#define PARENTSHARE       0  /* >0: parent keeps x/1024 of its weight on division, children share the rest, 0 = even split */
+#define COSTCROWD         0  /* crowding stress, per neighbor over CROWDLIMIT per turn, 0 = off           */
+#define CROWDRADIUS       1  /* cells around the bug that count as its neighborhood                      */
+#define CROWDLIMIT        3  /* neighbors tolerated before crowding stress sets in                       */
+#define NOMMASS        1024  /* nominal mass, costs are prorated according to COST*mass/NOMMASS          */
+#define GENECOST        128
+#define GENEKNEE         96  /* non-linearity inflection point, beyond the knee, genecost increases steeply */
+#define GENEEXP           3  /* steepness of the gene cost, genes cost GENECOST * ngenes^GENEEXP / GENEKNEE^(GENEEXP-1) */
+#define DECISIONSQUASH    0  /* >0: a gene term v counts as v*S/(|v|+S), never more than S, before the sum genes add on */
+#define RANDOMSTART       0  /* >0: bug one's chromosomes are each this many random genes, 0 = the hand built starter brain */
+#define RANDOMFACE        0  /* 1 = bug one starts facing a direction drawn from the seed, 0 = always east */
+#define IMMIGRATE         0  /* >0: every this many days, if there are fewer than IMMIGRATEBELOW bugs, fresh ones arrive */
+#define IMMIGRATEBELOW   64  /* population under which immigrants arrive                                 */
+#define IMMIGRANTS        4  /* bugs arriving each time, at random free cells                             */
+#define IMMIGRANTGENES    4  /* random genes in each of an immigrant's chromosomes                         */
+#define FREEZEGENES       0  /* 1 = disable mutation and recombination, offspring clone the parent, random draws are still taken so seeds stay aligned */
+#define EATLIMIT        205  /* allow eating 20% of body mass per turn                                   */
+#define EATREACH          0  /* x/1024 of the food in the faced cell is also in reach, 0 = own cell only */
+#define EATEFFICIENCY  1024  /* x/1024 of the food eaten becomes bug weight, 1024 = all of it            */
+#define DIETHIN      102400  /* limit at which bug starves and becomes bugfood                           */
+#define MASSCAP    10240000  /* above 10000, start the masscap tax                                       */
+#define MASSLIMIT 0x3FFFFFFF  /* hard limit on weight, eating stops adding weight here (~1M units)       */
+#define MASSTARGET    600.0  /* Food cutback when average bug exceeds MASSTARGET                         */
+#define ACTSLEEP          0  /* action index numbers                                                     */
+#define ACTEAT            1  /* action with the highest weight is the action taken                       */
+#define ACTTURNCW         2
+#define ACTTURNCCW        3
+#define ACTMOVE           4  /* if no bug in destination cell, simply move, otherwise fight bug in dest. */
+#define ACTMATE           5  /* if no bug in destination cell, waste COSTMATE energy and do nothing      */
+#define ACTDIVIDE         6
+#define BACKSTEP          0  /* 1 adds a step back without turning, decision 7, genomes are not compatible with 0 */
+#if BACKSTEP
+#define ACTBACKSTEP       7  /* step to the cell behind, only if it is empty, facing is kept             */
+#define LASTACT           ACTBACKSTEP
+#define RESPONSEMATE      8
+#define ACTMATED          8
+#define ACTDEFEND         9
+#define NACT             10
+#define NDECISIONS        9
+#else
+#define LASTACT           ACTDIVIDE  /* decisions 0 to LASTACT are the actions a bug chooses between       */
+#define RESPONSEMATE      7  /* if response mate >0, mate is the decision, otherwise, ignore             */  
+#define ACTMATED          7  /* also for logging, agressee does not use turn                             */
+#define ACTDEFEND         8  /* for history logging, does not consume turn                               */
+#define NACT              9  /* number of actions for history computation, response mate is not recorded */
+#define NDECISIONS        8  
+#endif
+#define ACTIONSPERDAY     1  /* turns each bug takes per day, a quicker bug decides and pays for each one */
+#define POSHISTORY       32  /* retain position history for 32 turns */
+#define ACTHISTORY       POSHISTORY  /* turns back a bug can sense its own actions, no more than POSHISTORY */
+#define ITEMFOOD          0  /* mass of food in cell */
+#define ITEMBUG           1  /* mass of bug in cell, 0 if none */
+#define ITEMBUGFACE       2  /* translated bug facing, or 0 if no bug */
+#define ITEMBUGMATCH      3  /* genetic match of bug 0-1.0, or 0 if no bug */
+#define DIR_E             0
+#define DIR_NE           -1
+#define DIR_NW           -2
+#define DIR_SE            1
+#define DIR_SW            2
+#define DIR_W             3
+#define DIR_CW            1
+#define DIR_CCW          -1
+#define NONE              0
+#define SCENT             0  /* 1 = bugs lay a scent trail, adds NSENSECELLS senses so genomes are not compatible with 0 */
+#define SCENTDEPOSIT   1024  /* scent left on the bug's cell each turn         */
+#define SCENTDECAY      973  /* scent remaining after a turn, x/1024            */
+#define SCENTSPREAD     128  /* share of the difference that flows to a weaker neighbor per turn, x/1024 */
+#define SENSERADIUS       0  /* >0 adds 6 far food senses, one per sector of the ring this far out, genomes are not compatible with 0 */
+#define POPPRESSURE       0  /* 1 adds a sense of the whole population against target_pop, genomes are not compatible with 0 */
+#define NSENSECELLS      12
+#define SENSESELF         NSENSECELLS * 6
+#define SPAWNWEIGHTNORM   NSENSECELLS * 6 + NACT  /* special purpose senses */
+#define STARVEWEIGHTNORM  SPAWNWEIGHTNORM + 1     
+#define SELFAGE           STARVEWEIGHTNORM + 1     
+#define THIRSTSENSE       SELFAGE + 1
+#define SCENTSENSE        THIRSTSENSE + 1    /* NSENSECELLS scent readings, same cell order as food, SCENT builds only */
+#if SCENT
+#define RINGSENSE         SCENTSENSE + NSENSECELLS
+#else
+#define RINGSENSE         SCENTSENSE
+#endif
+#if SENSERADIUS                              /* 6 ring sector food readings, clockwise from the sector ahead-right, SENSERADIUS builds only */
+#define POPSENSE          RINGSENSE + 6
+#else
+#define POPSENSE          RINGSENSE
+#endif
+#if POPPRESSURE                              /* population over target_pop, 1024 = on target, POPPRESSURE builds only */
+#define NSENSES           POPSENSE + 1
+#else
+#define NSENSES           POPSENSE           /* number of sense data points on which bug decisions are made */
+#endif
+#define GENECONST         1
+#define GENESENSE         2
+#define GENELIMIT         3
+#define GENECOMPARE       4
+#define GENEMATCH         5
+#define FAMHIST         126
+#define LHIST          1300
+#define SELFTESTDAYS     64    /* length of the "bugs selftest" run */
+#define STUCKWINDOW       0    /* >0: a run that stays flat this many days (< LHIST) is flagged as stuck, checked every 64 days */
+#define STUCKSTOP         0    /* 1 = end a stuck run with the final report */
+#define DEBUGINDEX        0    /* 1 = run check_world() after every day and abort() at the first problem, slow */
+#define PHASETRACE        0    /* 1 = print each phase of phases[] as one_day() runs it */
+#define PROFILE           0    /* 1 = time each phase with the monotonic clock, averages shown with the status line */
+#define LIFEBINS         16    /* lifespan histogram, bin n counts deaths at age 2^(n-1) to 2^n - 1, last bin is open ended */
+#define GENOMECLUSTERS    0    /* >0 = the bug report groups the population into up to this many genome clusters */
+#define CLUSTERDIST    8192    /* genome_distance() within which a bug joins a cluster, 1024 = one gene wholly different */
+#define DECPROFILE        0    /* 1 = tally decision wins and outputs in bugdecide() for the bug report */
+#define ETHNIC_DUR      120    /* Number of generations it takes to assimilate to the local color */
+#define CATFAMINE         1    /* catastrophes[] kinds: food cut to amount/1024 */
+#define CATFLOOD          2    /* water raised by amount */
+#define CATDIEOFF         3    /* each bug dies with chance amount/1024 */
+#define ETHBANDS          4    /* population by dominant color: red, green, blue, mixed (no color over half) */
+#define POP_TARGET    ( ( WORLD_X * WORLD_Y ) / 50 )
+#define POP_HARDLIMIT ( ( WORLD_X * WORLD_Y ) / 5 )
+#define MAX_SLOPE         ( DIETHIN / 4096 )
+#define DROWN_TIME        8    /* turns underwater before expiring */
+#define INIT_DEPTH        ( DIETHIN / 1024 )
+#define DROWN_DEPTH       ( DIETHIN / 256 )
+#define ALIVE             0    /* bug_alive() causes of death */
+#define DEATHSTARVE       1
+#define DEATHTHIRST       2
+#define DEATHDROWN        3
+
+// Settings that can't work together stop the build, ones that waste a run only warn - build with -Werror to stop on those too
+#if ACTHISTORY > POSHISTORY
+#error ACTHISTORY reaches past the POSHISTORY the bugs keep
+#endif
+#if FRAMEINTERVAL < 1
+#error FRAMEINTERVAL must be at least 1
+#endif
+#if STUCKWINDOW >= LHIST
+#error STUCKWINDOW must be shorter than the LHIST days of history
+#endif
+#if SPAWNRADIUS < 1
+#warning SPAWNRADIUS below 1 puts offspring on the cell of the parent, none will ever be born
+#endif
+#if PARENTSHARE >= 1024
+#warning PARENTSHARE 1024 or more leaves the children no weight, no division will succeed
+#endif
+#if EATEFFICIENCY <= 0
+#warning EATEFFICIENCY 0 means eating never adds weight, every bug will starve
+#endif
+#if ACTIONSPERDAY < 1
+#warning ACTIONSPERDAY below 1, no bug will ever move
+#endif
+#if PHENOMAP && !RECORDFRAMES
+#warning PHENOMAP frames are only saved when RECORDFRAMES is on
+#endif
+#if STUCKSTOP && ( STUCKWINDOW <= 0 )
+#warning STUCKSTOP does nothing without a STUCKWINDOW
+#endif
+#if ( TRACKLINEAGE >= 0 ) && ( COLORBY != COLORETH )
+#warning TRACKLINEAGE dims the COLORBY colors too, the family may be hard to pick out
+#endif
+#if ETHBANDS != 4
+#error ETHBANDS must stay 4, statname[] names the red, green, blue and mixed bands
+#endif
+
+struct _pos
+  { long x;      // 0 to WORLD_X - 1
+    long y;      // 0 to WORLD_Y - 1  
+  };
+
+struct _ethnicity
+  {  long uid;  // Unique id number - like a serial number
+     char r;    // ethnicity weights
+     char g;
+     char b;
+     char pad;  // to keep word alignment of data structure...
+  };
+ 
+struct _bugstate
+  {  struct _pos p;
+            long face;   // which way is the bug pointing (0=E 1=SE 2=SW 3=W -1=NE -2=NW)
+            long act;    // action which got us here 0-8
+            long weight; // dry weight at this time (think fixed point, value is *1024), drown in water>weight*10/1024
+	    long hydrate;  // water units on board, max is weight/1024, die at 0
+  };
+
+typedef struct _gene
+  {         long  tp;     // Gene type 1=constant, 2=sense, 3=limit function
+            long  si;     // index of the sense function to use (unless type==1)
+            long  c1,c2;  // Constants, c1 used in constant type, c1 and c2 used in limit function
+    struct _gene *next;   // Housekeeping link for adding and deleting genes from list
+    struct _gene *prev;   // Housekeeping link
+    struct _gene *prod;   // Evaluation link, value = vself * vprod + vsum
+    struct _gene *sum;    // Evaluation link, if *prod == null, vprod = 1.0, if *sum == null, vsum = 0.0
+  } Gene;
+
+struct _bugact            // One of these gene sets for each action
+  { struct _gene *a,*b;   // will build two lists of "genes", this structure is like a diploid "chromosome"
+    struct _ethnicity ea,eb;  // Track the origin of the gene's mutation
+  };
+
+struct _bugbrain
+  { struct _bugact act[NDECISIONS];    // decision weights
+ struct _ethnicity family[FAMHIST];
+ struct _ethnicity eth;
+              long generation;
+              long divide;             // number of children in a division DIVIDEMIN-DIVIDEMAX, counting the parent 
+             short ngenes;             // for all act.a and act.b lists, used to adjust cost of living
+             short expression;         // bitmap telling which chromosomes get used
+  };
+
+typedef struct _bugdata
+  {             long  birthday;        // calendar day of last division
+                long  kills;           // Other life history
+                long  defends;
+                long  moves;
+                long  mate_success;
+                long  mate_fails;
+                long  mate_rejects;    // the part of mate_fails where a partner was there but said no
+                long  mate_repeat;
+                long  offspring;
+                long  underwater;      // Number of turns since was last overwater
+                long  actcount[NACT];  // turns spent on each action, ACTMATED and ACTDEFEND count being mated and attacked
+    struct _bugstate  pos[POSHISTORY]; // 0 is current, others historical
+    struct _bugbrain  brain;           // decisionmaker data
+    struct _bugbrain  matebrain;       // decisionmaker of last mate, used in sexual procreation, copy of self at birth
+    struct  _bugdata *next;            // linked list forward pointer
+    struct  _bugdata *prev;            // linked list backward pointer
+  } Bugdata;
+
+struct _buglist
+  {            long  n_bugs;
+    struct _bugdata *first; 
+    struct _bugdata *endlist; 
+  };
+
+struct _worlddata  // data for one cell of the world, packed to 32 bytes - there are WORLD_X * WORLD_Y of these
+  { struct _bugdata *bug;      // quickfind for the bug in this cell
+               long  food;     // might add interesting weather, terrain and other things... later
+	        int  water;    // the world's whole water supply is well inside an int
+                int  elevation;
+                int  scent;    // trail left by passing bugs, only used when SCENT is on
+              short  nearest;  // distance to the nearest bug, -1 to 3, picks the rot[] for the food under it
+  };
+
+struct _catastrophe  // a disaster on a set day, over the cells x1..x2, y1..y2 (wrapping like bugs_in_rect)
+  { long day;      // 0 ends the table
+    long kind;     // CATFAMINE, CATFLOOD or CATDIEOFF
+    long x1,y1,x2,y2;
+    long amount;   // famine: food left x/1024, flood: water added per cell, dieoff: x/1024 of the bugs killed
+  };
+
+struct _vector  // a known run of the shipped defines, checked by the "verify" command
+  { long seed;     // RNGSEED for the run
+    long days;     // 0 ends the table
+    long n_bugs;   // bugs alive at the end
+    unsigned long long hash;  // state_hash() at the end
+  };
+
+struct _historydata
+  { long n_bugs;
+    long movement;
+    long collisions;
+    long starvations;
+    long drownings;
+    long births;
+    long avgweight;
+    long avgfood;
+    long avggenes;
+    long ethnic[ETHBANDS];
+  };
+
+//
+// global
+//
+  struct _worlddata  world[WORLD_X][WORLD_Y];
+  struct   _buglist  buglist;
+    struct _bugdata *nextglobalbug;
+struct _historydata  hist[LHIST];             // historical statistics by turn for graphs
+struct _catastrophe  catastrophes[] =         // the disaster schedule, in day order
+  { // { 5000, CATFLOOD,  0, 0, WORLD_X / 4, WORLD_Y - 1, 2048 },  // example: flood the west quarter
+    // { 8000, CATDIEOFF, 0, 0, WORLD_X - 1, WORLD_Y - 1,  512 },  // example: half the bugs die
+    { 0, 0, 0, 0, 0, 0, 0 }
+  };
+     struct _vector  vectors[] =              // only valid for the defines as shipped, recompute after changing any of them
+  { { 54321,  20,  5, 0x777c3a9fbd5bb08aULL },
+    { 12345,  40,  3, 0x767074cb1fee36b6ULL },
+    {   777,  60, 16, 0xe39a06bea93c149bULL },
+    { 0, 0, 0, 0 }
+  };
+               long  lifespan[LIFEBINS];      // histogram of age at death, all causes
+               long  decwins[NDECISIONS];     // DECPROFILE: times each decision was chosen since the last report
+               long  decsum[NDECISIONS];      // DECPROFILE: sum of each decision's output since the last report
+               long  decn;                    // DECPROFILE: number of decisions made since the last report
+               long  sense[NSENSES];          // shared array, used by all bugs one at a time to make decisions
+               long  today;                   // day counter
+               long  idcounter;               // unique bug identifier
+        double long  totalfood;               // status variable
+        double long  totalbug;                // status variable
+        double long  genecount;               // status variable
+               long  ethcount[ETHBANDS];      // status variable, bugs in each ethnic band
+               long  db = 0;                  // debug flag
+               long  leak = -1;               // Food leak, starts in on state, higher values makes leak stay farther from bug
+               long  geneknee = GENEKNEE;     // For dynamic genecost adjustment
+               long  forcemate = 0;
+               long  costmate = COSTMATE;
+               long  agediv = 0;              // must be at least agediv to successfully divide - stiff penalty otherwise (when forcemate >=5)
+               long  rot[4];
+               long  safety = 1;
+               long  target_pop = POP_TARGET;
+	       long  drink_or_die = 4;  // Water forgiveness factor, decreases over the years
+              float  foodhump = 1.4;
+               long  stage = 0;               // dynamic challenge progress
+               long  stagewait = 0;           // days to hold before the next stage can start
+               long  rngseed = RNGSEED;       // limitedrandom() state, reset_sim() starts it over
+               long  stuckday = 0;            // first day run_stuck() found the run flat, 0 = not stuck
+               long  rngdraws = 0;            // limitedrandom() calls so far, two runs that match here drew the same numbers
+   struct _bugbrain  frozenbrain;             // scratch brain that takes the mutations when FREEZEGENES is set
+
+// functions
+
+//
+// Hex grid movement translators - 0,0 is upper left corner of map
+//
+void east( struct _pos *p )
+{ if ( p->x < WORLD_X - 1 )
+    p->x++;
+   else
+    p->x = 0;
+}
+
+void southeast( struct _pos *p )
+{ if ( p->y % 2 == 0 )
+    { if ( p->x < WORLD_X - 1 )
+        p->x++;
+       else
+        p->x = 0;
+    }
+    
+  if ( p->y < WORLD_Y - 1 )
+    p->y++;
+   else
+    p->y = 0;
+}
+
+void northeast( struct _pos *p )
+{ if ( p->y % 2 == 0 )
+    { if ( p->x < WORLD_X - 1 )
+        p->x++;
+       else
+        p->x = 0;
+    }
+
+  if ( p->y > 0 )
+    p->y--;
+   else
+    p->y = WORLD_Y - 1;
+}
+
+void west( struct _pos *p )
+{ if ( p->x > 0 )
+    p->x--;
+   else
+    p->x = WORLD_X - 1;
+}
+
+void southwest( struct _pos *p )
+{ if ( p->y % 2 != 0 )
+    { if ( p->x > 0 )
+        p->x--;
+       else
+        p->x = WORLD_X - 1;
+    }
+
+  if ( p->y < WORLD_Y - 1 )
+    p->y++;
+   else
+    p->y = 0;
+}
+
+void northwest( struct _pos *p )
+{ if ( p->y % 2 != 0 )
+    { if ( p->x > 0 )
+        p->x--;
+       else
+        p->x = WORLD_X - 1;
+    }
+
+  if ( p->y > 0 )
+    p->y--;
+   else
+    p->y = WORLD_Y - 1;
+}
+
+//
+// In a WALLED world, is b, reached from a in no more than steps moves, really on the far side of an edge?
+// hexmove() always wraps, a wrapped cell shows up as much farther away than steps
+//
+long off_map( struct _pos *a, struct _pos *b, long steps )
+{ if ( !WALLED )
+    return 0;
+  if (( a->x - b->x > steps ) || ( b->x - a->x > steps ))
+    return 1;
+  if (( a->y - b->y > steps ) || ( b->y - a->y > steps ))
+    return 1;
+  return 0;
+}
+
+void hexmove( struct _pos *p , long dir )
+{ 
+  // Compensate for rollovers
+  while ( dir < DIR_SW ) 
+    dir += 6;
+  while ( dir > DIR_W )
+    dir -= 6;
+
+  switch ( dir )
+    { case DIR_NW: northwest( p ); break;
+      case DIR_NE: northeast( p ); break;
+      case DIR_E:       east( p ); break;
+      case DIR_SE: southeast( p ); break;
+      case DIR_SW: southwest( p ); break;
+      case DIR_W:       west( p ); break;
+    }
+}
+
+//
+// Count the bugs within radius cells of p, not counting p itself
+// walks each hex ring: start radius steps to the NW, then radius steps in each direction, clockwise from E
+//
+long count_neighbors( struct _pos *p, long radius )
+{ struct _pos cp;
+         long r,i,j,n;
+
+  n = 0;
+  for ( r = 1 ; r <= radius ; r++ )
+    { cp = *p;
+      for ( j = 0 ; j < r ; j++ )
+        hexmove( &cp, DIR_NW );
+      for ( i = 0 ; i < 6 ; i++ )
+        for ( j = 0 ; j < r ; j++ )
+          { if (( world[cp.x][cp.y].bug != NULL ) && !off_map( p, &cp, radius ))
+              n++;
+            hexmove( &cp, DIR_E + i );
+          }
+    }
+  return n;
+}
+
+long myabs( long v )
+{ if ( v>=0 ) return v;
+  return -v;
+}
+
+long limitedrandom( long limit )
+{ 
+  rngdraws++;
+  rngseed = myabs( ((rngseed + 12355) * 16807) ) % 0x3FFFFFFF;
+
+  return (((unsigned long)rngseed >> 8) % limit);
+}
+        
+
+//
+// Value noise on the starting food - random levels on a coarse lattice, smoothly blended between lattice points
+// the lattice wraps, so there is no seam at the world edges
+//
+void food_noise( void )
+{ long x,y,nx,ny,i,j,fx,fy,v;
+  long *lat;
+
+  nx = WORLD_X / FOODNOISECELL; if ( nx < 1 ) nx = 1;
+  ny = WORLD_Y / FOODNOISECELL; if ( ny < 1 ) ny = 1;
+  lat = (long *)malloc( nx * ny * sizeof( long ) );
+  for ( i = 0 ; i < nx * ny ; i++ )
+    lat[i] = limitedrandom( 1025 ) - 512;  // -512 to 512
+
+  for ( x = 0 ; x < WORLD_X ; x++ )
+    for ( y = 0 ; y < WORLD_Y ; y++ )
+      { i  = (x * nx) / WORLD_X;                  // lattice cell
+        j  = (y * ny) / WORLD_Y;
+        fx = ((x * nx * 1024) / WORLD_X) % 1024;  // position within it, 0-1023
+        fy = ((y * ny * 1024) / WORLD_Y) % 1024;
+        v  = ( lat[ i           * ny + j] * (1024 - fx) + lat[((i+1) % nx) * ny + j] * fx ) / 1024 * (1024 - fy)
+           + ( lat[ i           * ny + (j+1) % ny] * (1024 - fx) + lat[((i+1) % nx) * ny + (j+1) % ny] * fx ) / 1024 * fy;
+        v  = v / 1024;                            // -512 to 512
+        world[x][y].food = FOODSTART + ( (FOODSTART / 100) * FOODNOISE * v ) / 512;
+        if ( world[x][y].food < 0 )
+          world[x][y].food = 0;
+      }
+
+  free( lat );
+}
+
+void init_world( void )
+{ long x,y;
+  for ( x = 0 ; x < WORLD_X ; x++ )
+    for ( y = 0 ; y < WORLD_Y ; y++ )
+      { world[x][y].food      = FOODSTART;
+        world[x][y].bug       = NULL;
+	world[x][y].elevation = 0;
+	world[x][y].water     = INIT_DEPTH;
+	world[x][y].scent     = 0;
+      }
+  today           = 0;
+  idcounter       = 0;
+  buglist.n_bugs  = 0;
+  buglist.first   = NULL;
+  buglist.endlist = NULL;
+
+  for ( x = 0 ; x < LHIST ; x++ )
+    { hist[x].n_bugs      =
+      hist[x].movement    =
+      hist[x].collisions  =
+      hist[x].starvations =
+      hist[x].drownings   =
+      hist[x].births      =
+      hist[x].avgweight   =
+      hist[x].avgfood     =
+      hist[x].avggenes    = 0;
+      for ( y = 0 ; y < ETHBANDS ; y++ )
+        hist[x].ethnic[y] = 0;
+    }
+
+  for ( x = 0 ; x < LIFEBINS ; x++ )
+    lifespan[x] = 0;
+  for ( x = 0 ; x < NDECISIONS ; x++ )
+    decwins[x] = decsum[x] = 0;
+  decn = 0;
+
+  if ( FOODNOISE > 0 )
+    food_noise();
+}
+
+//
+// Scenario authoring - set the contents of one cell
+// coordinates wrap like hexmove, food is held to the FOODCAP * 10 hard limit, water can't go negative
+//
+void set_cell( long x, long y, long food, long water, long elevation )
+{ x = ((x % WORLD_X) + WORLD_X) % WORLD_X;
+  y = ((y % WORLD_Y) + WORLD_Y) % WORLD_Y;
+
+  if ( food  < 0 )           food  = 0;
+  if ( food  > FOODCAP * 10 ) food  = FOODCAP * 10;
+  if ( water < 0 )           water = 0;
+
+  world[x][y].food      = food;
+  world[x][y].water     = water;
+  world[x][y].elevation = elevation;
+}
+
+//
+// Read a scenario file, if there is one, and set the cells it lists
+//
+void load_scenario( char *fname )
+{ FILE *fp;
+  long  x,y,food,water,elevation,n;
+
+  fp = fopen( fname, "r" );
+  if ( fp == NULL )
+    return;  // No scenario, plain world
+
+  n = 0;
+  while ( fscanf( fp, "%ld %ld %ld %ld %ld", &x, &y, &food, &water, &elevation ) == 5 )
+    { set_cell( x, y, food, water, elevation );
+      n++;
+    }
+  fclose( fp );
+  printf( "%ld cells set from %s\n", n, fname );
+}
+
+//
+// Paint the world from an image, if there is one - red is elevation around 128, green food and blue water
+// on the same scales the environment map draws them, an image of another size is stretched over the world
+//
+void load_seed_image( char *fname )
+{ FILE *fp;
+  gdImagePtr  im;
+        long  x,y,c,b;
+
+  fp = fopen( fname, "rb" );
+  if ( fp == NULL )
+    return;  // No seed image, plain world
+  im = gdImageCreateFromPng( fp );
+  fclose( fp );
+  if ( im == NULL )
+    { printf( "%s is not a PNG, ignored\n", fname );
+      return;
+    }
+
+  for ( x = 0 ; x < WORLD_X ; x++ )
+    for ( y = 0 ; y < WORLD_Y ; y++ )
+      { c = gdImageGetTrueColorPixel( im, (x * gdImageSX( im )) / WORLD_X, (y * gdImageSY( im )) / WORLD_Y );
+        b = gdTrueColorGetBlue( c );
+        set_cell( x, y, ( gdTrueColorGetGreen( c ) * FOODCAP ) / 192, ( b > 64 ) ? b - 64 : 0, gdTrueColorGetRed( c ) - 128 );
+      }
+  printf( "World painted from %s, %dx%d\n", fname, gdImageSX( im ), gdImageSY( im ) );
+  gdImageDestroy( im );
+}
+
+//
+// Repeatable noise 0-1023 for a cell on a given day, mixed from the coordinates
+// does not draw on limitedrandom(), so using it doesn't shift the rest of the run
+//
+long cell_noise( long x, long y, long t )
+{ unsigned long h;
+
+  h  = ((unsigned long)x * 73856093UL) ^ ((unsigned long)y * 19349663UL) ^ ((unsigned long)t * 83492791UL);
+  h ^= h >> 13;
+  h *= 0x5bd1e995UL;
+  h ^= h >> 15;
+
+  return (long)(h % 1024);
+}
+
+//
+// Determine the seasonal (latitude) growth factor
+// Including cosine terrain factor
+//
+long growing_season( long x, long y )
+{  long sax;
+  float fgf;
+
+  sax = (x + ( today * WORLD_X ) / SEASONLENGTH) % WORLD_X;   // Seasonally adjusted X
+
+  fgf = 0.1 + foodhump * sin( (3.14159 * ((float)sax)) / ((float)WORLD_X) ) * ( 0.51 - cos( 3.14159 * 6.0 * ((float)y) / ((float)WORLD_Y) ) * 0.5 );
+  return (long)(((float)FOODGROW-1024) * fgf) + 1024;
+}
+
+
+void update_nearest( void )
+{ long x,y,i,j;
+  struct _pos p;
+
+  // set zeroes in all cells with bugs, -1 in others
+  for ( x = 0 ; x < WORLD_X ; x++ )
+    for ( y = 0 ; y < WORLD_Y ; y++ )
+      { if ( world[x][y].bug == NULL )
+          world[x][y].nearest = -1;
+         else 
+          world[x][y].nearest = 0;
+      }
+
+  // establish distance to nearest bug - up to 1 bug away for now
+//  for ( j = 0 ; j < 3 ; j++ )
+//  j = 0;
+//    for ( x = 0 ; x < WORLD_X ; x++ )
+//      for ( y = 0 ; y < WORLD_Y ; y++ )
+//        { if ( world[x][y].nearest == j )
+//            for ( i = -2 ; i <= 3 ; i++ )
+//              { p.x = x;
+//                p.y = y; 
+//                hexmove( &p, i );
+//                if ( world[p.x][p.y].nearest == -1 )  // Only update cells not yet marked (less or equal)
+//                  world[p.x][p.y].nearest = j + 1;
+//              }
+//        }                
+}
+  
+// flow_water has some inherent "viscocity" in the algorithm, it will take many turns for a high spot to level out
+void flow_water( void )
+{ long v,x,y,i,j,w,flow;
+  double long level;
+  struct _pos p,cp;
+
+   for ( v = 0 ; v < 4 ; v++ )  // Higher v count means lower viscocity
+   for ( x = 0 ; x < WORLD_X ; x++ )
+     for ( y = 0 ; y < WORLD_Y ; y++ )
+       { j = limitedrandom(6);  // Brownian randomness in the flow direction preference, preventing a unidirectional flow preference.
+         cp.x = x;
+         cp.y = y;
+         for ( i = -2 ; i <= 3 ; i++ ) // All 6 points of the "compass"
+           { p.x = x;
+             p.y = y; 
+             hexmove( &p, i + j );
+             if ( off_map( &cp, &p, 1 ) )
+               continue;  // no flow through the wall
+	     
+	     // MAX_SLOPE erosion implementation
+	     if ( world[p.x][p.y].elevation > (world[x][y].elevation + MAX_SLOPE) )
+	       { world[p.x][p.y].elevation--;
+	         world[  x][  y].elevation++;
+	       }
+	     if ( world[x][y].elevation > (world[p.x][p.y].elevation + MAX_SLOPE) )
+	       { world[p.x][p.y].elevation++;
+	         world[  x][  y].elevation--;
+	       }
+	       
+	     w = world[p.x][p.y].water + world[x][y].water;  // available water
+             if ( w > 0 )  // No water, nothing to do.
+	       { if ( (world[p.x][p.y].water + world[p.x][p.y].elevation) != (world[x][y].water + world[x][y].elevation) ) 
+	           { // Equalize with this neighbor
+	             level = (world[p.x][p.y].water + world[p.x][p.y].elevation + world[x][y].water + world[x][y].elevation) / 2;  // desired final level
+		 
+		     if ( world[p.x][p.y].elevation < world[x][y].elevation )
+		       { flow = level - world[p.x][p.y].elevation - world[p.x][p.y].water;
+		         world[p.x][p.y].water = level - world[p.x][p.y].elevation; // Fill to desired level
+		         if ( world[p.x][p.y].water > w )
+			   { world[p.x][p.y].water = w; // drained completely in
+		             world[  x][  y].water = 0;
+			     world[p.x][p.y].elevation++;  // dry peak erosion
+	                     world[  x][  y].elevation--;
+			   }
+			 else if ( world[p.x][p.y].water < 0 )  // Not sure this case is possible, but it doesn't hurt to check
+			   { world[p.x][p.y].water = 0; // drained completely out
+		             world[  x][  y].water = w;
+			   }
+			 else 
+			   { world[x][y].water = w - world[p.x][p.y].water;
+			     flow = flow / MAX_SLOPE;
+			     if ( flow > 0 )  // fast flow erosion
+			       { world[p.x][p.y].elevation += flow;
+			         world[  x][  y].elevation -= flow;
+			       }
+			   }
+		       }
+		      else  // go the other way
+		       { flow = level - world[x][y].elevation - world[x][y].water;
+		         world[x][y].water = level - world[x][y].elevation; // Fill to desired level
+		         if ( world[x][y].water > w )
+			   { world[  x][  y].water = w; // drained completely in
+		             world[p.x][p.y].water = 0;
+			     world[p.x][p.y].elevation--;  // dry peak erosion
+	                     world[  x][  y].elevation++;
+			   }
+			 else if ( world[x][y].water < 0 )  // Not sure this case is possible, but it doesn't hurt to check
+			   { world[  x][  y].water = 0; // drained completely out
+		             world[p.x][p.y].water = w;
+			   }
+			 else 
+			   { world[p.x][p.y].water = w - world[x][y].water;
+			     flow = flow / MAX_SLOPE;
+			     if ( flow > 0 )  // fast flow erosion
+			       { world[  x][  y].elevation += flow;
+			         world[p.x][p.y].elevation -= flow;
+			       }
+			   }
+		       }    
+		   }
+	       }     
+           }
+       }                
+}
+
+
+//
+// Coarse lineage - which of the three home colors dominates the ethnicity, band 3 if none holds half
+//
+long eth_band( struct _ethnicity *e )
+{ if ( e->r * 2 > ETHNIC_DUR ) return 0;
+  if ( e->g * 2 > ETHNIC_DUR ) return 1;
+  if ( e->b * 2 > ETHNIC_DUR ) return 2;
+  return 3;
+}
+
+void grow_food( void )
+{ long x,y,i,t;
+  struct _pos p,cp;
+    long fgl,cap;
+   
+  cap = FOODCAP;
+  if ( FOODHALFLIFE > 0 )  // the land wears out, the soft cap halves every FOODHALFLIFE days
+    cap = (long)( FOODCAP * pow( 0.5, (double)today / (double)FOODHALFLIFE ) );
+
+  update_nearest();
+  flow_water();  // Growth of food is independant of availability of water, in 0.26
+
+  totalfood = 0;
+  totalbug  = 0;
+  genecount = 0;
+  for ( i = 0 ; i < ETHBANDS ; i++ )
+    ethcount[i] = 0;
+
+  for ( y = 0 ; y < WORLD_Y ; y++ )
+    { 
+
+    for ( x = 0 ; x < WORLD_X ; x++ )
+      { cp.x = x;
+        cp.y = y;
+        fgl = growing_season( x, y );
+        if ( FOODJITTER > 0 )  // knock this cell's growth a little off its neighbors'
+          fgl = 1024 + ((fgl - 1024) * (1024 + (FOODJITTER * (cell_noise( x, y, today ) - 512)) / 512)) / 1024;
+
+        if (( world[x][y].nearest == -1 ) || ( leak < world[x][y].nearest ))  // Don't grow the grass when a bug is on it (discourages sedentary bugs)
+          world[x][y].food = (world[x][y].food * fgl) / 1024;
+         else
+          world[x][y].food = (world[x][y].food * rot[world[x][y].nearest] ) / 1024;  // Infact, decay when bug is squatting (even nearby)
+
+        if ( world[x][y].food > cap )
+          world[x][y].food -= ((world[x][y].food - cap) * FOODDECAY) / 1024;  // Decay 10% of overage per turn - this means foodcap is not a hard limit
+	  
+        if ( world[x][y].food > FOODCAP * 10 )
+          world[x][y].food = FOODCAP * 10;  // This is a hard limit
+	  
+        totalfood += world[x][y].food / 1024; // more or less
+
+        if ( world[x][y].bug != NULL ) 
+          { totalbug  += world[x][y].bug->pos[0].weight; // for reporting
+            genecount += world[x][y].bug->brain.ngenes;
+            ethcount[ eth_band( &(world[x][y].bug->brain.eth) ) ]++;
+          }
+
+        // spread to nearby cells that have less than 50% of this one
+        for ( i = -2 ; i <= 3 ; i++ )
+          { p.x = x;
+            p.y = y;
+            hexmove( &p, i );
+            if ( off_map( &cp, &p, 1 ) )
+              continue;
+            if ( world[p.x][p.y].food < world[x][y].food / 16 )
+              { if (( world[p.x][p.y].nearest == -1 ) || ( leak < world[p.x][p.y].nearest ))
+                  { t = ( world[x][y].food * FOODSPREAD ) / 1024;
+                    world[x][y].food -= t;
+                    world[p.x][p.y].food += t;
+                  }
+              }
+          }     
+
+        if ( SCENT && ( world[x][y].scent > 0 ) )
+          { world[x][y].scent = (world[x][y].scent * SCENTDECAY) / 1024;
+            for ( i = -2 ; i <= 3 ; i++ )  // diffuse downhill, what leaves this cell arrives next door
+              { p.x = x;
+                p.y = y;
+                hexmove( &p, i );
+                if ( off_map( &cp, &p, 1 ) )
+                  continue;
+                if ( world[p.x][p.y].scent < world[x][y].scent )
+                  { t = ((world[x][y].scent - world[p.x][p.y].scent) * SCENTSPREAD) / 1024;
+                    world[x][y].scent   -= t;
+                    world[p.x][p.y].scent += t;
+                  }
+              }
+          }
+           
+    } }
+}
+
+//
+// Search a range for matches, return the number found
+//
+long range_match( struct _bugbrain *b1, struct _bugbrain *b2, long s1, long e1, long s2, long e2 )
+{ long c2,m;
+
+  m = 0;
+  while( s1 <= e1 )
+    { c2 = s2;
+      while ( c2 <= e2 )
+        { if ( b1->family[s1].uid == b2->family[c2].uid )
+            m++;
+          c2++;
+        }
+      s1++;
+    }
+
+  return m;
+}
+
+
+//
+// return a number indicating relationship - brother sister = 1024
+//
+long family_match( struct _bugbrain *b1, struct _bugbrain *b2, long level )
+{ long r;
+  
+  if ( level == 0 ) return 1024;  // self = total match
+
+  r = 0;
+  // Mother father matches add 256 each, grandparents 64, etc.
+  r += range_match( b1, b2,  0,  1,  0,  1 ) * 256; if ( r == 512 ) return 1024; // same 2 parents, total match
+  if ( level == 3 ) return r;
+  r += range_match( b1, b2,  2,  5,  2,  5 ) * 64;    // 256 poss
+  if ( level == 2 ) return r;
+  r += range_match( b1, b2,  6, 13,  6, 13 ) * 16;    // 128 poss
+  r += range_match( b1, b2, 14, 29, 14, 29 ) * 4;     //  64 poss
+  r += range_match( b1, b2, 30, 62, 30, 62 );         //  32 poss
+  // best information on closest bug (potential mate)
+
+  return r;
+
+}
+//
+// For the passed bug, arrange processed sense data into sense[] global
+//
+void gather_senses( struct _bugdata *bug )
+{ struct _pos cp;
+         long i,j,f,level;
+
+
+  // cells  ahead 1,2,5
+  // cells to  cw 4,8,9,11
+  // cells to ccw 3,6,7,10
+
+  for ( i = 0 ; i < NSENSECELLS ; i++ )
+    { // navigate to the cells
+      cp = bug->pos[0].p;
+      switch ( i )
+        { case  0:                                                                                                       level = 0; break;
+          case  1: hexmove( &cp, bug->pos[0].face );                                                                     level = 1; break;
+          case  2: hexmove( &cp, bug->pos[0].face ); hexmove( &cp, bug->pos[0].face );                                   level = 2; break;
+          case  3: hexmove( &cp, bug->pos[0].face + DIR_CCW );                                                           level = 2; break;
+          case  4: hexmove( &cp, bug->pos[0].face + DIR_CW  );                                                           level = 2; break;
+          case  5: hexmove( &cp, bug->pos[0].face ); hexmove( &cp, bug->pos[0].face ); hexmove( &cp, bug->pos[0].face ); level = 3; break;
+          case  6: hexmove( &cp, bug->pos[0].face + DIR_CCW ); hexmove( &cp, bug->pos[0].face + DIR_CCW );               level = 3; break;
+          case  7: hexmove( &cp, bug->pos[0].face + DIR_CCW ); hexmove( &cp, bug->pos[0].face );                         level = 3; break;
+          case  8: hexmove( &cp, bug->pos[0].face + DIR_CW  ); hexmove( &cp, bug->pos[0].face );                         level = 3; break;
+          case  9: hexmove( &cp, bug->pos[0].face + DIR_CW  ); hexmove( &cp, bug->pos[0].face + DIR_CW  );               level = 3; break;
+          case 10: hexmove( &cp, bug->pos[0].face + DIR_CCW * 2 );                                                       level = 3; break;
+          case 11: hexmove( &cp, bug->pos[0].face + DIR_CW  * 2 );                                                       level = 3; break;
+        }
+
+       // Normalize the food mass to the bug mass
+       if ( bug->pos[0].weight <= 0 ) 
+         bug->pos[0].weight = 1;  // Protect against potential divide by zeroes
+
+       if ( off_map( &(bug->pos[0].p), &cp, 3 ) )  // past the wall, an empty cell at our level
+         { sense[i] = sense[i+NSENSECELLS] = sense[i+NSENSECELLS*2] = sense[i+NSENSECELLS*3] = 0;
+           sense[i+NSENSECELLS*4] = sense[i+NSENSECELLS*5] = 0;
+#if SCENT
+           sense[i+SCENTSENSE] = 0;
+#endif
+           continue;
+         }
+
+       sense[i] = (long)(((long long)world[cp.x][cp.y].food * 1024) / bug->pos[0].weight);                        // Available food
+       sense[i+NSENSECELLS*4] = world[cp.x][cp.y].water;                                                          // Water depth
+       sense[i+NSENSECELLS*5] = world[cp.x][cp.y].elevation - world[bug->pos[0].p.x][bug->pos[0].p.y].elevation;  // Relative elevation
+#if SCENT
+       sense[i+SCENTSENSE] = world[cp.x][cp.y].scent;                                                            // Scent trail
+#endif
+
+       // is there a bug in the cell?
+       if ( world[cp.x][cp.y].bug == NULL )
+         { // No, set zero values
+           sense[i+NSENSECELLS  ] = 0;
+           sense[i+NSENSECELLS*2] = 0;
+           sense[i+NSENSECELLS*3] = 0;
+         }
+        else
+         { // Normalize the other bug mass to this bug mass
+           sense[i+NSENSECELLS] = (long)(((long long)world[cp.x][cp.y].bug->pos[0].weight * 1024) / bug->pos[0].weight);
+           // Orient the other bug facing relative to this bug facing (0 = facing same direction)
+           f = world[cp.x][cp.y].bug->pos[0].face - bug->pos[0].face;
+           while ( f < -2 ) f += 6;
+           while ( f >  3 ) f -= 6;
+           // normalize facing value -2048 -> 3072 to scale similarly to mass and other decision variables
+           sense[i+NSENSECELLS*2] = f * 1024;
+           // Family history match 0-1024
+           sense[i+NSENSECELLS*3] = family_match( &(world[cp.x][cp.y].bug->brain), &(bug->brain), level );
+         }
+    }   
+
+  // Add the self-awareness variables
+  for ( i = 0 ; i < NACT ; i++ )
+    { j = 0;
+      while ( j < ACTHISTORY ) 
+        { if ( bug->pos[j].act == i )                              // Time since act has happened
+            { sense[i+NSENSECELLS*6] = (j * 1024) / ACTHISTORY;    // Normalized so length of history = 1.0
+              j = ACTHISTORY;
+            }
+           else
+            { if ( j == ACTHISTORY-1 ) 
+                sense[i+NSENSECELLS*6] = 1024;                     // This act is not in history
+            }
+          j++;
+        }
+    }
+  // Might also add a sum of number of times an act has happened in history...
+
+  sense[  SPAWNWEIGHTNORM ] = (long)(((long long)(( bug->pos[0].weight / bug->brain.divide ) - COSTDIVIDE ) * 1024 ) / DIETHIN);  // an answer of 1024 means division would yield offspring that must eat immediately or die
+  sense[ STARVEWEIGHTNORM ] = (long)(((long long)bug->pos[0].weight * 1024 ) / DIETHIN);  // an answer of 1024 means we're dying of starvation
+  sense[          SELFAGE ] = today - bug->birthday;
+  sense[      THIRSTSENSE ] = bug->pos[0].hydrate;
+
+#if SENSERADIUS
+  // Far food, the ring SENSERADIUS out split in 6 sectors relative to facing
+  // sector i starts at the corner SENSERADIUS steps toward face + i and runs clockwise to the next corner
+  for ( i = 0 ; i < 6 ; i++ )
+    { long long ring = 0;  // a few cells at MASSLIMIT overflow a 32 bit long
+      cp = bug->pos[0].p;
+      for ( j = 0 ; j < SENSERADIUS ; j++ )
+        hexmove( &cp, bug->pos[0].face + i );
+      for ( j = 0 ; j < SENSERADIUS ; j++ )
+        { if ( !off_map( &(bug->pos[0].p), &cp, SENSERADIUS ) )
+            ring += world[cp.x][cp.y].food;
+          hexmove( &cp, bug->pos[0].face + i + 2 );
+        }
+      sense[RINGSENSE + i] = (long)(((ring / SENSERADIUS) * 1024) / bug->pos[0].weight);  // Average available food, same scale as the near cells
+    }
+#endif
+
+#if POPPRESSURE
+  sense[ POPSENSE ] = ( buglist.n_bugs * 1024 ) / target_pop;  // crowding of the whole world, not just the neighbors
+#endif
+  
+  // Might add a travel history... many other things
+  // for ( k = 1; k < POSHISTORY ; k++ )
+  //   { i = bug->pos[0].p.x - bug->pos[POSHISTORY - 1].p.x; i = i*i;
+  //     j = bug->pos[0].p.y - bug->pos[POSHISTORY - 1].p.y; j = j*j;
+  //   }
+
+ 
+}
+
+//
+// limit function
+// 
+long limit_fn( long x, long l1, long l2 )
+{ 
+  if ( l1 <= l2 )
+    { if ( x < l1 ) return 0;
+      if ( x > l2 ) return 1024;
+
+       // inbetween, do a linear interpolation
+       if ( l1 = l2 ) return 512;
+       return (1024 * ( x - l1 )) / (l2 - l1); 
+    }
+
+  // l1 is greater than l2, invert the limit structure
+  if ( x < l2 ) return 1024;
+  if ( x > l1 ) return 0;
+
+  return 1024 - (1024 * ( x - l2 )) / (l1 - l2); 
+}  
+
+
+long evaluate_gene( struct _gene *g )
+{ long v;
+
+  if ( g->si < 0 )       { g->si = 0;                        printf("Hosed.\n"); }
+  if ( g->si > NSENSES ) { g->si = limitedrandom( NSENSES ); printf("Hosed.\n"); } // this should never happen, but...
+
+  switch ( g->tp )
+    { case GENECONST:
+        v = g->c1;
+        break;
+ 
+      case GENESENSE:
+        v = (sense[g->si] * g->c1) / 1024 + g->c2;
+        break;
+
+      case GENELIMIT:
+      default:
+        v = limit_fn( sense[g->si], g->c1, g->c2 );
+        break;
+
+      case GENECOMPARE:
+        v = (sense[g->si] - sense[(g->c1) % NSENSES]) + g->c2;
+
+      case GENEMATCH:
+        v = 1024 - myabs((sense[g->si] - sense[(g->c2) % NSENSES]) * g->c1) / 1024;
+        if ( v < 0 ) v = 0;
+    }
+  
+  if ( g->prod != NULL )
+    v = (v * evaluate_gene( g->prod )) / 1024;
+
+  if ( DECISIONSQUASH > 0 )  // soft cap on any one gene's say
+    v = (v * DECISIONSQUASH) / (myabs( v ) + DECISIONSQUASH);
+
+  if ( g->sum != NULL )
+    v += evaluate_gene( g->sum );
+
+  return v;
+}
+
+//
+// Evaluate the current sense[] to decide on an action
+//
+long bugdecide( struct _bugbrain *brp )
+{ long actv[NDECISIONS];
+  long i,j,x,v,maxv;
+
+  maxv = -1048576;
+  j = 0;
+  x = 1;
+  for ( i = 0 ; i <= LASTACT ; i++ )
+    { if ( (brp->expression & x) != 0 )
+        v = evaluate_gene( brp->act[i].a ); 
+       else 
+        v = evaluate_gene( brp->act[i].b );
+      x = x * 2;
+      if ( DECPROFILE )
+        decsum[i] += v;
+      if ( v > maxv )
+        { maxv = v;
+          j = i;
+        }
+    }
+  if ( DECPROFILE )
+    { decwins[j]++;
+      decn++;
+    }
+  return j;
+}
+
+//
+// Genes are charged as extra mass - about GENECOST per gene at the knee, rising steeply beyond it
+//
+long gene_mass( long ngenes )
+{ long i,m,k;
+
+  m = GENECOST;
+  k = 1;
+  for ( i = 0 ; i < GENEEXP ; i++ )
+    m *= ngenes;
+  for ( i = 1 ; i < GENEEXP ; i++ )
+    k *= geneknee;
+
+  return m / k;
+}
+
+//
+// adjust the mass for normalized cost extraction
+// 
+void costcalc( long cost, struct _bugdata *bug )
+{ long mass;
+  long long total;
+
+  mass = myabs( bug->pos[0].weight ) + gene_mass( bug->brain.ngenes );  // For cost calculations each gene is counted as additional mass
+
+  // Special obesity tax 1% per unit over masscap (100 units over doubles cost of everything, 200 triples, etc.)
+  if ( mass > MASSCAP )
+    cost = cost * (1 + (mass - MASSCAP)/102400);
+   
+  total = ((long long)cost * mass) / NOMMASS;  // both can be large on a fat bug, don't overflow a 32 bit long
+  if ( total > MASSLIMIT )                     // no bug weighs more, so no bigger cost can matter
+    total = MASSLIMIT;
+  mass = (long)total;
+
+  if ( mass < 100 )  printf ("too cheap! cost=%ld, weight=%ld, genes=%ld, tc=%ld\n", cost,bug->pos[0].weight / 1024, bug->brain.ngenes , mass);
+
+  bug->pos[0].weight -= mass;
+  if ( bug->pos[0].weight <= 0 )
+    bug->pos[0].weight = 1;
+    
+  // Also, extract some water, transpire it into the air, and rain it down on the world randomly
+  mass = mass / 1024;  // Adjust cost for water calculations
+  mass = mass / drink_or_die;  // Go easy on water requirements, at first
+  
+  if ( mass > bug->pos[0].hydrate )
+    mass = bug->pos[0].hydrate;  // Don't over extract!  water is conserved
+    
+  bug->pos[0].hydrate -= mass;
+  world[limitedrandom(WORLD_X)][limitedrandom(WORLD_Y)].water += mass; // rain down somewhere
+}
+
+
+//
+// Free the genes in this list
+//
+void free_genes( struct _gene *g )
+{ struct _gene *n;
+
+  while ( g != NULL )
+    { n = g->next;
+      free( g );
+      g = n;
+    }
+}
+
+//
+// Free the genes from this brain
+//
+void free_brain( struct _bugbrain *bp )
+{ long i;
+  
+  for ( i = 0 ; i < NDECISIONS ; i++ )
+    { free_genes( bp->act[i].a );
+      free_genes( bp->act[i].b );
+      bp->act[i].a = NULL;
+      bp->act[i].b = NULL;
+    }
+  bp->ngenes = 0;
+}
+
+//
+// Determine ethnicity based on parents and position
+//
+void det_ethnicity( struct _ethnicity *offs, struct _ethnicity *mom, struct _ethnicity *dad, struct _pos *p )
+{
+  offs->r = (mom->r + dad->r) / 2;
+  offs->g = (mom->g + dad->g) / 2;
+  offs->b = (mom->b + dad->b) / 2;
+
+  // Slowly assimilate
+  switch ( (p->y * 3) / WORLD_Y )
+    { case 0: // Blue sky land at the top
+        if ( offs->r > 0 ) { offs->r--; offs->b++; }
+        if ( offs->g > 0 ) { offs->g--; offs->b++; }
+        while ( (offs->r + offs->g + offs->b) < ETHNIC_DUR ) offs->b++;
+        break;
+      case 1: // Redlands in the middle
+        if ( offs->g > 0 ) { offs->g--; offs->r++; }
+        if ( offs->b > 0 ) { offs->b--; offs->r++; }
+        while ( (offs->r + offs->g + offs->b) < ETHNIC_DUR ) offs->r++;
+        break;
+      default:
+      case 2: // Greenland at the bottom
+        if ( offs->r > 0 ) { offs->r--; offs->g++; }
+        if ( offs->b > 0 ) { offs->b--; offs->g++; }
+        while ( (offs->r + offs->g + offs->b) < ETHNIC_DUR ) offs->g++;
+        break;
+    }
+ 
+}
+
+
+long countgenes( struct _gene *g )
+{ long i;
+  
+  i = 0;
+  while ( g != NULL )
+    { g = g->next;
+      i++;
+    }
+  return i;
+}
+
+
+struct _gene *cclp = NULL; // used in the recursive function copy_chromosome, should be initialized to NULL by calling function before call
+//
+// Copy a gene list, creating new genes and linking as we go
+// 
+struct _gene *copy_chromosome( struct _gene *g )
+{ struct _gene *gp;
+
+  if ( g != NULL )
+    {  gp       = (Gene *)malloc( sizeof( struct _gene ) );       // allocate new structure
+      *gp       = *g;                                            // copy the constants
+       gp->next = NULL;                                         // maintain the list
+       gp->prev = cclp;                                        // use the global to link to last allocated gene
+       if ( cclp != NULL )                                    // fix next link in previous gene
+         gp->prev->next = gp;
+       cclp     = gp;                                       // ready for next gene
+       gp->prod = copy_chromosome( g->prod );              // copy the branches
+       gp->sum  = copy_chromosome( g->sum  );
+       return gp;                                        // pointer to base of this sub-tree
+    }
+   else
+    return NULL;
+}
+
+//
+// Copy brain contents, from a to b
+//
+void copy_brain( struct _bugbrain *a, struct _bugbrain *b )
+{ long  i;
+
+  free_brain( b );
+  *b = *a;  // copy the constants, including ngenes
+  // now, do the linked lists
+  for ( i = 0 ; i < NDECISIONS ; i++ )
+    { cclp = NULL; b->act[i].a = copy_chromosome( a->act[i].a );
+      cclp = NULL; b->act[i].b = copy_chromosome( a->act[i].b );
+    }
+
+}
+
+long genesdropped;  // global counter for the recursive function disposebranch
+
+//
+// Prune the chromosome starting at g
+// dispose of all prod and sum subterms
+// and keep the next prev list maintained
+// 
+// note that g MUST NOT be the first gene in the tree (prev MUST NOT == NULL)
+//
+void disposebranch( struct _gene *g )
+{ if ( g->prev == NULL )
+    return; 
+
+
+  if ( g->prod != NULL )
+    disposebranch( g->prod );
+  if ( g->sum != NULL )
+    disposebranch( g->sum );
+  
+  if ( g->next != NULL )     // fix the list
+    g->next->prev = g->prev;
+  g->prev->next = g->next;
+  free( g );
+  genesdropped++;            // keep track
+}
+
+
+
+//
+// Make a gene value tweak - or two or more
+//
+void tweakgene( struct _gene *g )
+{ long d,r;
+
+  r = 1 + limitedrandom( 255 );  // 50% chance of 1 tweak, 25% chance of 2 tweaks, 12% chance of 3 tweaks, 6% chance of 4....
+
+  while ( r < 256 )
+    {
+
+          switch ( limitedrandom( 4 ) )
+            { case 0: // Change gene type to one of the other gene types - note that the other values will either become active or dormant based on start and end types
+                g->tp += limitedrandom( 4 ) + 1;
+                if ( g->tp > 5 ) g->tp -= 5;
+                break;
+               
+              case 1: // Change the sense index
+                d = limitedrandom( NSENSES + 6 ) - 3;  // give slight preference to near 3 choices
+                if ( d == 0 ) d = 6;                  // no change not allowed
+                g->si += d;
+                if ( g->si < 0         ) g->si += NSENSES;
+                if ( g->si > NSENSES-1 ) g->si = g->si % NSENSES;
+                break;
+              
+              case 2: 
+                d = 1024 + limitedrandom( 256 ) - 128;
+                g->c1 = (g->c1 * d) / 1024 + limitedrandom( 128 ) - 64;
+                break;
+
+              case 3: 
+                d = 1024 + limitedrandom( 256 ) - 128;
+                g->c2 = (g->c2 * d) / 1024 + limitedrandom( 128 ) - 64;
+                break;
+            }                
+      r *= 2;
+    }
+
+}
+
+
+//
+// Make a number of mutations
+//
+void mutatebrain( struct _bugbrain *brain )
+{ long n,c,s,d,r;
+  struct _gene *g; 
+  struct _gene *g2; 
+  struct _gene *gn; 
+  struct _ethnicity *ep;
+
+  if ( FREEZEGENES && ( brain != &frozenbrain ) )
+    { // Mutate a throwaway copy, so the random number stream matches an unfrozen run
+      copy_brain( brain, &frozenbrain );
+      mutatebrain( &frozenbrain );
+      free_brain( &frozenbrain );
+      return;
+    }
+
+  r = 1 + limitedrandom( 16383 );  // Up to 14 mutations, very low chance of higher numbers though
+
+  while ( r < 16384 )  // 50% chance 1, 25% chance 2, etc...
+    {
+
+  n = limitedrandom( NDECISIONS + 1 );
+
+  if ( n == NDECISIONS )
+    { // mess with divide
+      brain->divide += limitedrandom( 3 ) - 1; // Keep the change small 
+      if ( brain->divide > DIVIDEMAX ) brain->divide = ( DIVIDEMAX > DIVIDEMIN ) ? DIVIDEMAX - 1 : DIVIDEMAX;  // bounce off the edges
+      if ( brain->divide < DIVIDEMIN ) brain->divide = ( DIVIDEMAX > DIVIDEMIN ) ? DIVIDEMIN + 1 : DIVIDEMIN;
+    }
+   else
+    { // n determines the chromosome number
+      if ( limitedrandom( 2 ) ) // determine chain a or b
+        { g  =   brain->act[n].a;
+          ep = &(brain->act[n].ea);
+        }
+       else
+        { g  =   brain->act[n].b;
+          ep = &(brain->act[n].eb);
+        }
+      *ep = brain->eth;        // Record the bug that had this mutation for coloring purposes in the report
+      g2 = g;                  // head of chain, for later work
+      c = countgenes( g );
+      c = limitedrandom( c );  // Choose a gene to act on
+      while ( c > 0 )
+        { g = g->next;
+          c--;
+        }
+      if ( limitedrandom( 2 ) )
+        { tweakgene( g );
+        } 
+       else
+        { if ( limitedrandom( 4 ) != 0 )
+            { // add a gene - a copy of the randomly selected g
+              d = 0;
+              while ( !d )  // randomly traverse the prod sum tree to an empty end node
+                { s = limitedrandom( 2 );
+                  if ( s )
+                    { if ( g2->prod == NULL )
+                        d = 1;
+                       else
+                        g2 = g2->prod;
+                    }
+                   else
+                    { if ( g2->sum == NULL )
+                        d = 1;
+                       else
+                        g2 = g2->sum;
+                    }
+                }
+              gn = (Gene *)malloc( sizeof( struct _gene ) );
+              brain->ngenes++;
+              if ( s )
+                g2->prod = gn;
+               else
+                g2->sum  = gn;
+              *gn = *g;          // get the coefficients
+              gn->sum  = NULL;  // fix up the pointers
+              gn->prod = NULL;
+              gn->next = NULL;
+              while ( g->next != NULL )  // traverse to end of the list starting at g
+                g = g->next;
+               g->next = gn;
+              gn->prev = g;
+              if ( limitedrandom( 2 ) )   // 50/50 chance the new gene will be tweaked
+                tweakgene( gn );
+            }
+           else
+            { // prune the chromosome at g, if it has any prod or sum children
+              // note that this will never clip off the first gene in the chain
+              if ( g->prod != NULL )
+                { if ( g->sum != NULL )
+                    s = limitedrandom( 2 ); // both present, choose randomly
+                   else
+                    s = 1;                  // only prod
+                }
+               else
+                { if ( g->sum != NULL )
+                    s = 0;                  // only sum
+                   else
+                    s = 2;
+                }
+              genesdropped = 0;
+              if ( s == 0 ) { disposebranch( g->sum  ); g->sum  = NULL; }
+              if ( s == 1 ) { disposebranch( g->prod ); g->prod = NULL; }
+              brain->ngenes -= genesdropped;
+    }   }   }
+
+    r *= 2;
+
+    }
+   
+}
+              
+                       
+//
+// A blank bug on the map at p, on the end of the list with the next uid, life history zeroed
+// the caller fills in the brain, family and ethnicity - every bug is made here, so no two share a uid
+//
+struct _bugdata *new_bug( struct _pos *p, long face, long weight, long hydrate )
+{ struct _bugdata *bug;
+             long  i;
+
+  bug = (Bugdata *)malloc( sizeof( struct _bugdata ) );
+  world[p->x][p->y].bug = bug;
+
+  bug->next = NULL;
+  bug->prev = buglist.endlist;
+  if ( buglist.endlist == NULL )
+    buglist.first = bug;
+   else
+    buglist.endlist->next = bug;
+  buglist.endlist = bug;
+  buglist.n_bugs++;
+
+  bug->brain.eth.uid = idcounter++;        // The bug social security number
+  bug->birthday       = today;
+  bug->kills          = 0;
+  bug->defends        = 0;
+  bug->mate_success   = 0;
+  bug->mate_repeat    = 0;
+  bug->mate_fails     = 0;
+  bug->mate_rejects   = 0;
+  bug->moves          = 0;
+  bug->offspring      = 0;
+  bug->underwater     = 0;
+  for ( i = 0 ; i < NACT ; i++ )
+    bug->actcount[i] = 0;
+  for ( i = POSHISTORY-1 ; i >= 0 ; i-- )
+    { bug->pos[i].p       = *p;
+      bug->pos[i].face    = face;
+      bug->pos[i].act     = ACTSLEEP;
+      bug->pos[i].weight  = weight;
+      bug->pos[i].hydrate = hydrate;
+    }
+  return bug;
+}
+
+//
+// This bug has died - turn its mass into food and free its allocated structures
+// 
+void kill_bug( struct _bugdata *bug )
+{ long x,y,i,age;
+
+  x = bug->pos[0].p.x;
+  y = bug->pos[0].p.y;
+
+  age = today - bug->birthday;  // File the lifespan in its power of 2 bin
+  for ( i = 0 ; ( age > 0 ) && ( i < LIFEBINS - 1 ) ; i++ )
+    age /= 2;
+  lifespan[i]++;
+
+  if ( bug == nextglobalbug )
+    nextglobalbug = bug->next;
+
+  world[x][y].food  += bug->pos[0].weight;
+  world[x][y].water += bug->pos[0].hydrate;  // Reclaim the water right here
+  world[x][y].bug    = NULL;
+
+  free_brain( &(bug->brain)     );
+  free_brain( &(bug->matebrain) );
+
+  // Remove the bug from the list
+  buglist.n_bugs--;
+  if ( buglist.n_bugs == 0 )  // that was the last one
+    { buglist.first   = NULL;
+      buglist.endlist = NULL;
+    }
+  if ( buglist.n_bugs > 0 )
+    { if ( bug->prev == NULL )       // Was first in list
+        {
+          buglist.first   = bug->next;
+          if ( buglist.first == NULL )   // No more bugs!
+            buglist.endlist = NULL;
+           else
+            bug->next->prev = NULL;
+        }
+       else
+        { if ( bug->next == NULL )  // Was last in list
+            { bug->prev->next = NULL;
+              buglist.endlist = bug->prev;
+            }
+           else
+            { bug->prev->next = bug->next; // Was in middle of list
+              bug->next->prev = bug->prev;
+    }   }   }
+
+  free( bug );
+}
+
+//
+// Can the bug go on living?  ALIVE, or the cause of death - starving first, then thirst, then drowning
+// combat deaths are settled in the fight itself
+//
+long bug_alive( struct _bugdata *bug )
+{ if ( bug->pos[0].weight < DIETHIN )
+    return DEATHSTARVE;
+  if ( bug->pos[0].hydrate <= 0 )
+    return DEATHTHIRST;
+  if ( bug->underwater > DROWN_TIME )
+    return DEATHDROWN;
+  return ALIVE;
+}
+
+//
+// bug move - act, do all energy accounting, mate and fight resolution, etc.
+// returns 0 if the bug died on this move
+//
+long bug_move( struct _bugdata *bug )
+{             long  i,j,mass,face,ngenes,wetness,watercons,reach,cost;
+       struct _pos  p,q;
+   struct _bugdata *defender;
+   struct _bugdata *offspring;
+
+
+  if ( SCENT )
+    world[bug->pos[0].p.x][bug->pos[0].p.y].scent += SCENTDEPOSIT;  // mark the trail
+
+  gather_senses( bug );
+
+  // shift history back 1 space
+  for ( i = POSHISTORY-1 ; i > 0 ; i-- )
+    bug->pos[i] = bug->pos[i-1]; 
+
+  i = bugdecide( &(bug->brain) ); 
+  bug->pos[0].act = i;
+  bug->actcount[i]++;
+  switch( i )
+    { case ACTSLEEP:  // Sleep now becomes sleep / drink - 0.26
+        cost = COSTSLEEP;
+        if ( SLEEPRECOVER > 0 )  // a bug that settles in to sleep burns less than one that naps between moves
+          { for ( j = 1 ; ( j < POSHISTORY ) && ( bug->pos[j].act == ACTSLEEP ) ; j++ )
+              cost -= ( cost * SLEEPRECOVER ) / 1024;
+            if ( cost < COSTSLEEP / 4 )
+              cost = COSTSLEEP / 4;
+          }
+        costcalc( cost, bug );
+	p = bug->pos[0].p;
+	if ( bug->pos[0].hydrate < (bug->pos[0].weight / 1024) )  // Can't drink when already full
+	  { if ( world[p.x][p.y].water > 0 )  // Can't drink when there's no water
+	      { // Try to drink it all!
+	        bug->pos[0].hydrate += world[p.x][p.y].water;
+		world[p.x][p.y].water = 0;
+		if ( bug->pos[0].hydrate > (bug->pos[0].weight / 1024) )  // Over full?
+		  { // Give some back
+		    world[p.x][p.y].water = bug->pos[0].hydrate - (bug->pos[0].weight / 1024);
+		    bug->pos[0].hydrate = (bug->pos[0].weight / 1024);
+		  }
+	      }
+	  }
+        break;
+
+      case ACTEAT:
+        mass = (long)(((long long)bug->pos[0].weight * EATLIMIT) / 1024);  // limit food intake to EATLIMIT% of body weight
+	p = bug->pos[0].p;
+        q = p;
+        reach = 0;
+        if ( EATREACH > 0 )  // graze the cell ahead too
+          { hexmove( &q, bug->pos[0].face );
+            if ( !off_map( &p, &q, 1 ) )
+              reach = (long)(((long long)world[q.x][q.y].food * EATREACH) / 1024);
+          }
+        if ( mass > world[p.x][p.y].food + reach ) // and amount of food available in this cell
+          { bug->pos[0].weight -= (mass - world[p.x][p.y].food - reach); // Penalty for overeating
+            mass = world[p.x][p.y].food + reach;
+          }
+        bug->pos[0].weight += (long)(((long long)mass * EATEFFICIENCY) / 1024);   // the rest of the meal is lost in digestion
+        if ( bug->pos[0].weight > MASSLIMIT )                      // eating is the only way to gain weight
+          bug->pos[0].weight = MASSLIMIT;
+        if ( mass > world[p.x][p.y].food )      // own cell first, the rest from ahead
+          { world[q.x][q.y].food -= mass - world[p.x][p.y].food;
+            mass = world[p.x][p.y].food;
+          }
+        world[p.x][p.y].food      -= mass;      // move the mass from land to bug
+	world[p.x][p.y].elevation--;
+	world[limitedrandom(WORLD_X)][limitedrandom(WORLD_Y)].elevation++;
+        costcalc( COSTEAT, bug );                                  // and pay for lunch
+        break;
+
+      case ACTTURNCW:
+        if ( bug->pos[0].face < 3 )
+          bug->pos[0].face += 1;
+         else
+          bug->pos[0].face = -2;
+        costcalc( COSTTURN, bug );
+        p = bug->pos[0].p;
+	world[p.x][p.y].elevation--;
+	world[limitedrandom(WORLD_X)][limitedrandom(WORLD_Y)].elevation++;
+        break;
+
+      case ACTTURNCCW:
+        if ( bug->pos[0].face > -2 )
+          bug->pos[0].face -= 1;
+         else
+          bug->pos[0].face = 3;
+        costcalc( COSTTURN, bug );
+        p = bug->pos[0].p;
+	world[p.x][p.y].elevation--;
+	world[limitedrandom(WORLD_X)][limitedrandom(WORLD_Y)].elevation++;
+        break;
+
+      case ACTMOVE:
+        bug->moves++;
+        hist[today % LHIST].movement++;
+        p = bug->pos[0].p;
+	world[p.x][p.y].elevation--;
+	world[limitedrandom(WORLD_X)][limitedrandom(WORLD_Y)].elevation++;
+        hexmove( &p , bug->pos[0].face );     // the destination
+        defender = world[p.x][p.y].bug;       // check for squatters
+        costcalc( COSTMOVE, bug );            // pay for the move
+        if ( bug->pos[0].weight < 0 ) bug->pos[0].weight = 0;  
+        if ( off_map( &(bug->pos[0].p), &p, 1 ) )
+          break; // Walked into the wall, stays put
+        if ( defender != NULL )
+          { if ( safety ) break; // No kills while safety is on
+
+            hist[today % LHIST].collisions++;
+            mass = defender->pos[0].weight;
+	    i = defender->pos[0].face - bug->pos[0].face;       // deermine relative facing
+            while ( i < -2 ) i += 6; while ( i >  3 ) i -= 6;  // correct for overflow
+            switch ( i )
+              { case 0:
+                  mass *= (defender->defends / 2) + 1;  // Experience points
+                  mass /= 128;                         // Head on, advantage to the defender
+                  break;
+                case  1:
+                case -1:                                // Oblique from the front, no adjustment
+                  mass *= (defender->defends / 4) + 1; // Experience points
+                  mass /= 1024;
+                  break;
+                case  2:
+                case -2:
+                  mass *= (defender->defends / 8) + 1; // Experience points
+                  mass /= 8192;                       // oblique from the rear, advantage to the attacker
+                  mass -= bug->kills;                // Experience advantage to the attacker
+                  break;
+                case 3:
+                  mass /= 65536;                    // from the rear, BIG advantage to the attacker - experience doesn't help
+                  mass -= bug->kills * bug->kills; // BIG Experience advantage to the attacker
+                  break;
+              }
+            if ( mass < 0 ) mass = 0;
+            if ( limitedrandom( mass + (bug->pos[0].weight / 1024) ) > mass )
+              { // victory
+                bug->kills++;
+                kill_bug( defender );
+                world[p.x][p.y].bug = bug;                                 // move in
+                world[bug->pos[0].p.x][bug->pos[0].p.y].bug = NULL;        // erase link from where we were
+                bug->pos[0].p = p;                                         // locate ourselves
+                costcalc( COSTFIGHT, bug );         // pay for the fight
+              }
+             else
+              { // defeat
+                defender->defends++;
+                world[p.x][p.y].food += bug->pos[0].weight;
+                bug->pos[0].weight = 0;                                         // note bring food to the defender
+                kill_bug( bug ); 
+                bug = NULL;
+                world[p.x][p.y].bug = defender;     // fix the link to the victor
+                // shift history of defender back 1 space
+                for ( i = POSHISTORY-1 ; i > 0 ; i-- )
+                  defender->pos[i] = defender->pos[i-1]; 
+                defender->pos[0].act = ACTDEFEND;
+                defender->actcount[ACTDEFEND]++;
+              }
+          }
+         else
+          { // Do the move! - no defender
+            world[p.x][p.y].bug = bug;                                 // move in
+            world[bug->pos[0].p.x][bug->pos[0].p.y].bug = NULL;        // erase link from where we were
+            bug->pos[0].p = p;                                         // locate ourselves
+          }      
+        break;
+
+#if BACKSTEP
+      case ACTBACKSTEP:  // a retreat, never a fight
+        bug->moves++;
+        hist[today % LHIST].movement++;
+        p = bug->pos[0].p;
+	world[p.x][p.y].elevation--;
+	world[limitedrandom(WORLD_X)][limitedrandom(WORLD_Y)].elevation++;
+        hexmove( &p , bug->pos[0].face + 3 );  // the cell behind
+        costcalc( COSTMOVE, bug );
+        if ( bug->pos[0].weight < 0 ) bug->pos[0].weight = 0;  
+        if ( off_map( &(bug->pos[0].p), &p, 1 ) || ( world[p.x][p.y].bug != NULL ) )
+          break; // Backed into the wall or another bug, stays put
+        world[p.x][p.y].bug = bug;
+        world[bug->pos[0].p.x][bug->pos[0].p.y].bug = NULL;
+        bug->pos[0].p = p;
+        break;
+#endif
+
+      case ACTMATE:
+        p = bug->pos[0].p;
+        hexmove( &p , bug->pos[0].face );      // location of the potential mate
+        if (( world[p.x][p.y].bug != NULL ) && !off_map( &(bug->pos[0].p), &p, 1 ))
+          { // gather_senses( world[p.x][p.y].bug ); decided to view the world through the suitor's eyes, but make own decision // senses to make the decision - might add a few for mate identification, kinda confusing as it is
+            if ( evaluate_gene( world[p.x][p.y].bug->brain.act[RESPONSEMATE].a ) +     
+                 evaluate_gene( world[p.x][p.y].bug->brain.act[RESPONSEMATE].b ) > 0 )   // NOTE!!! RESPONSEMATE IS A WEIRD DOUBLE ACTING CHROMOSOME - different from the others
+              { // success, swap brains for later use during division
+                if ( bug->matebrain.eth.uid != world[p.x][p.y].bug->brain.eth.uid )
+                  bug->mate_success++;
+                 else
+                  bug->mate_repeat++;
+                if ( bug->brain.eth.uid != world[p.x][p.y].bug->matebrain.eth.uid )
+                  world[p.x][p.y].bug->mate_success++;
+                 else
+                  world[p.x][p.y].bug->mate_repeat++;
+                copy_brain( &(world[p.x][p.y].bug->brain), &(bug->matebrain) );
+                copy_brain( &(bug->brain), &(world[p.x][p.y].bug->matebrain) );
+                // shift history of mate back 1 space
+                for ( j = POSHISTORY-1 ; j > 0 ; j-- )
+                  world[p.x][p.y].bug->pos[j] = world[p.x][p.y].bug->pos[j-1]; 
+                world[p.x][p.y].bug->pos[0].act = ACTMATED;
+                                bug->pos[0].act = ACTMATED;
+                world[p.x][p.y].bug->actcount[ACTMATED]++;  // the initiator already counted its ACTMATE
+              }
+             else
+              { bug->mate_fails++;
+                bug->mate_rejects++;  // turned down
+              }
+          }
+         else
+          bug->mate_fails++;
+        costcalc( costmate, bug );
+        break;
+             
+      case ACTDIVIDE:
+        if ( forcemate & 0x10 )  // enforce agediv
+          if ( bug->birthday + agediv > today )
+            { if ( forcemate & 0x40 )
+                bug->pos[0].weight /= bug->brain.divide;
+              if ( forcemate & 0x20 )
+                bug->pos[0].weight -= COSTDIVIDE;  // Higher cost for higher forcemate levels
+              if ( bug->pos[0].weight < DIETHIN )
+                bug->pos[0].weight = DIETHIN;
+              costcalc( COSTSLEEP, bug );
+              break;
+            }
+        if ( forcemate & 0x01 )  // enforce "forcemate before div"
+          if ( bug->brain.eth.uid == bug->matebrain.eth.uid )
+            { if ( forcemate & 0x08 )
+                bug->pos[0].weight /= bug->brain.divide;
+              if ( forcemate & 0x04 )
+                bug->pos[0].weight -= COSTDIVIDE;  // Higher cost for higher forcemate levels
+              if ( bug->pos[0].weight < DIETHIN )
+                bug->pos[0].weight = DIETHIN;
+              costcalc( COSTSLEEP, bug );
+              break;
+            }
+        if ( PARENTSHARE > 0 )  // parent keeps its share, the children split the rest evenly
+          { mass = (long)(((long long)bug->pos[0].weight * (1024 - PARENTSHARE)) / 1024) / (bug->brain.divide - 1) - COSTDIVIDE;
+            bug->pos[0].weight = (long)(((long long)bug->pos[0].weight * PARENTSHARE) / 1024) - COSTDIVIDE;
+          }
+         else
+          { mass = (bug->pos[0].weight / bug->brain.divide) - COSTDIVIDE; 
+            bug->pos[0].weight = mass;
+          }
+	watercons = bug->pos[0].hydrate;
+	wetness = (watercons / bug->brain.divide) - 1;
+	if ( wetness < 0 ) wetness = 0;
+        if (( mass < DIVIDEMASS ) || ( bug->pos[0].weight < DIETHIN ))
+          break;
+        bug->pos[0].hydrate = wetness;  watercons -= wetness;
+	  
+ 
+        for ( i = 1 ; i < bug->brain.divide ; i++ )  // # of children
+          { p    = bug->pos[0].p;
+            face = bug->pos[0].face;
+            switch( i ) // rearranged.
+              { case 1: face = face + 3; break;
+                case 2: face = face - 2; break;
+                case 3: face = face + 2; break;
+                case 4: face = face - 1; break;
+                case 5: face = face + 1; break;
+                case 6: break;
+              }
+            while ( face < DIR_NW ) face += 6;  // keep facing in range, turns assume -2 to 3
+            while ( face > DIR_W  ) face -= 6;
+            for ( j = 0 ; j < SPAWNRADIUS ; j++ )  // straight out from the parent
+              hexmove( &p , face );
+
+            if (( world[p.x][p.y].bug == NULL ) && !off_map( &(bug->pos[0].p), &p, SPAWNRADIUS ))  // If space not empty, offspring is never born
+              {
+            bug->offspring++;
+            hist[today % LHIST].births++;
+
+            // Create new life - a new bug, with traits of brain and matebrain - and possibly a mutation 
+            offspring = new_bug( &p, face, mass, wetness );
+            if ( bug->brain.generation > bug->matebrain.generation )
+              offspring->brain.generation = bug->brain.generation + 1;
+             else
+              offspring->brain.generation = bug->matebrain.generation + 1;
+            offspring->brain.family[0] = bug->brain.eth;
+            offspring->brain.family[1] = bug->matebrain.eth;
+            det_ethnicity( &(offspring->brain.eth), &(bug->brain.eth), &(bug->matebrain.eth), &p );
+            j = 2;
+            while ( j + 1 < FAMHIST )
+              { offspring->brain.family[ j ] = bug->brain.family[(j/2)-1];
+                offspring->brain.family[j+1] = bug->matebrain.family[(j/2)-1];
+                j += 2;
+              }
+
+	    watercons -= wetness;  
+            ngenes = 0;
+            for ( j = 0 ; j < NDECISIONS ; j++ )
+              { if ( FREEZEGENES )  // a clone of the parent, the draws are still taken so seeds stay aligned
+                  { limitedrandom( 2 );
+                    limitedrandom( 2 );
+                    cclp = NULL; offspring->brain.act[j].a = copy_chromosome( bug->brain.act[j].a ); offspring->brain.act[j].ea = bug->brain.act[j].ea;
+                    cclp = NULL; offspring->brain.act[j].b = copy_chromosome( bug->brain.act[j].b ); offspring->brain.act[j].eb = bug->brain.act[j].eb;
+                  }
+                 else
+                  { if ( limitedrandom( 2 ) )  // a haploid chromosome comes from parent
+                      { cclp = NULL; offspring->brain.act[j].a = copy_chromosome( bug->brain.act[j].a ); offspring->brain.act[j].ea = bug->brain.act[j].ea; }
+                     else
+                      { cclp = NULL; offspring->brain.act[j].a = copy_chromosome( bug->brain.act[j].b ); offspring->brain.act[j].ea = bug->brain.act[j].eb; }
+
+                    if ( limitedrandom( 2 ) ) // b haploid chromosome comes from mate
+                      { cclp = NULL; offspring->brain.act[j].b = copy_chromosome( bug->matebrain.act[j].a ); offspring->brain.act[j].eb = bug->matebrain.act[j].ea; }
+                     else
+                      { cclp = NULL; offspring->brain.act[j].b = copy_chromosome( bug->matebrain.act[j].b ); offspring->brain.act[j].eb = bug->matebrain.act[j].eb; }
+                  }
+
+                ngenes += countgenes( offspring->brain.act[j].a );
+                ngenes += countgenes( offspring->brain.act[j].b );
+                offspring->matebrain.act[j].a = NULL;
+                offspring->matebrain.act[j].b = NULL;
+              }
+            offspring->brain.ngenes = ngenes;
+            if ( limitedrandom( 2 ) || FREEZEGENES )
+              offspring->brain.divide = bug->brain.divide;
+             else
+              offspring->brain.divide = bug->matebrain.divide;
+
+            offspring->brain.expression = limitedrandom( 1 << NDECISIONS );  // tempting to make this environmentally dependent...
+            if ( FREEZEGENES )
+              offspring->brain.expression = bug->brain.expression;
+
+            copy_brain( &(offspring->brain), &(offspring->matebrain) );  // also copies the ngenes and ndivide values
+
+            if ( limitedrandom( 4 ) == 0 )
+              mutatebrain( &(offspring->matebrain) ); // asexual reproduction has a good chance of catching a mutation
+            if ( limitedrandom( 8 ) == 0 )
+              mutatebrain( &(offspring->brain) );    // even 100% sexual reproducers have a chance of mutation
+              }
+
+
+          }
+	world[limitedrandom(WORLD_X)][limitedrandom(WORLD_Y)].water += watercons;  // rain down excess water somewhere
+        if ( forcemate & 0x02 )
+          bug->matebrain.eth.uid = bug->brain.eth.uid;
+        break;
+    }
+
+    
+  if ( bug != NULL )   // could have been killed in a fight
+    { if ( COSTCROWD > 0 )  // Density dependent stress
+        { i = count_neighbors( &(bug->pos[0].p), CROWDRADIUS ) - CROWDLIMIT;
+          if ( i > 0 )
+            costcalc( COSTCROWD * i, bug );
+        }
+
+      if ( world[bug->pos[0].p.x][bug->pos[0].p.y].water > DROWN_DEPTH ) // underwater?
+        bug->underwater++;
+       else 
+        bug->underwater = 0;
+
+      switch ( bug_alive( bug ) )
+        { case DEATHSTARVE:
+          case DEATHTHIRST:
+            hist[today % LHIST].starvations++;
+	    if ( today < 100 )  // report the cause while the bug is still here to ask
+	      { if (bug->pos[0].hydrate <= 0)
+	          printf("thirst.\n");
+	         else
+	          printf("hunger.\n");
+	      }
+            kill_bug( bug ); // too thin or dry to live, feed the grass
+            return 0;
+
+          case DEATHDROWN:
+            hist[today % LHIST].drownings++;
+            kill_bug( bug ); // underwater too long to live, feed the grass
+	    if ( today < 100 )
+	      printf("drowned.\n");
+            return 0;
+	}    
+    } 
+  return ( bug != NULL );
+}
+
+//
+// Move all bugs
+//
+void move_bugs( void )
+{
+  struct _bugdata *bug;
+             long  k;
+
+  bug = buglist.first;
+  while ( bug != NULL )
+    { nextglobalbug = bug->next;  // Incase this one is killed during the move
+      for ( k = 0 ; k < ACTIONSPERDAY ; k++ )  // each action is a whole turn, deciding, paying and checking for death
+        if ( !bug_move( bug ) )
+          break;
+      bug = nextglobalbug;
+    }
+
+}
+
+//
+// Simple add gene function for bug_one - cannot build trees, only a chain
+// gene created gets put at the head of the chain and pointer returned
+//
+struct _gene *add_gene( long tp, long si, long c1, long c2, struct _gene *og, long p )
+{ struct _gene *ng;
+  
+  ng = (Gene *)malloc( sizeof( struct _gene ) );
+  ng->tp = tp;
+  ng->si = si;
+  ng->c1 = c1;
+  ng->c2 = c2;
+  ng->next = og;
+  ng->prev = NULL;
+  ng->prod = NULL;
+  ng->sum  = NULL;
+
+  if ( og != NULL )
+    { og->prev = ng;
+      if ( p == 0 )
+        ng->sum  = og;
+       else
+        ng->prod = og;
+    }
+
+  return ng;
+}
+  
+//
+// A gene of any type, sense and constants, chained onto og like add_gene()
+//
+struct _gene *random_gene( struct _gene *og )
+{ long tp,si,c1,c2;
+
+  tp = GENECONST + limitedrandom( GENEMATCH );  // GENECONST to GENEMATCH
+  si = limitedrandom( NSENSES );
+  c1 = limitedrandom( 4097 ) - 2048;
+  c2 = limitedrandom( 4097 ) - 2048;
+  return add_gene( tp, si, c1, c2, og, limitedrandom( 2 ) );
+}
+
+//
+// Model bugs
+//
+// 0a>[ 0,1,055,   226,  1343,p- ,s- ]
+// 1a>[ 0,3,057,  1216,  1084,p- ,s 1][ 1,5,057,  1216,  1084,p- ,s- ]
+// 2a>[ 0,4,020,   629,   830,p 1,s- ][ 1,5,042,   555,   668,p 2,s- ][ 2,4,025,   636,   829,p- ,s- ]
+// 3b>[ 0,1,011,   319,  1426,p- ,s- ]
+// 4b>[ 0,3,000,   173,   -53,p- ,s 1][ 1,3,058,  4273,  2187,p- ,s- ]
+// 5b>[ 0,2,055,  1421,   456,p 1,s- ][ 1,2,013,   734,   101,p- ,s- ]
+// 6t>[ 0,5,024,  1754,  2063,p- ,s 1][ 1,4,015,  1231,   936,p 2,s- ][ 2,3,046,  1071,  2179,p- ,s- ]
+// 7b>[ 0,3,011,   -50,   591,p- ,s- ]
+
+// 0a>[ 0,3,055,   363,  1530,p- ,s- ]
+// 1a>[ 0,3,057,  1203,  1056,p- ,s- ]
+// 2a>[ 0,4,020,   615,   830,p 1,s- ][ 1,5,042,   525,   697,p 2,s- ][ 2,5,025,   658,   764,p- ,s- ]
+// 3b>[ 0,1,016,   341,  1570,p- ,s- ]
+// 4b>[ 0,3,000,   226,   -76,p- ,s 1][ 1,3,058,  3944,  2187,p- ,s- ]
+// 5a>[ 0,2,055,  1339,   567,p 1,s- ][ 1,2,013,   785,   101,p- ,s- ]
+// 6t>[ 0,5,024,  1901,  2063,p- ,s 1][ 1,3,045,  1170,   845,p 2,s- ][ 2,3,055,  1071,  1916,p- ,s- ]
+// 7a>[ 0,3,051,   -79,   546,p- ,s- ]
+
+
+
+
+//
+// Create the original bug
+//
+void bug_one( void )
+{ struct _bugdata *bug;
+  struct     _pos  p;
+             long  i,j,f;
+
+  p.x = WORLD_X / 2;
+  p.y = WORLD_Y / 2;
+  f = DIR_E;
+  if ( RANDOMFACE )  // no eastward lean in the first dispersal, only draws when on so default seeds stay aligned
+    f = limitedrandom( 6 ) - 2;
+  bug = new_bug( &p, f, DIETHIN * 256, DIETHIN / 4 );  // Fat, happy, ready to make children
+  
+  for ( i = 0 ; i < FAMHIST ; i++ )
+    { bug->brain.family[i].uid = -1;             // Unknown history
+      bug->brain.family[i].r   = ETHNIC_DUR / 8;
+      bug->brain.family[i].g   = ETHNIC_DUR / 8;
+      bug->brain.family[i].b   = ETHNIC_DUR / 8;
+    }
+
+  bug->brain.generation = 0;
+  bug->brain.divide     = 3;  // 2 offspring per division - parent just loses weight
+  if ( bug->brain.divide < DIVIDEMIN ) bug->brain.divide = DIVIDEMIN;
+  if ( bug->brain.divide > DIVIDEMAX ) bug->brain.divide = DIVIDEMAX;
+
+  bug->brain.eth.r = ETHNIC_DUR;
+  bug->brain.eth.g = 0;
+  bug->brain.eth.b = 0;
+
+  bug->brain.ngenes = 0;
+  bug->brain.expression = 0;  // was left to malloc(), only a fresh heap gave 0
+  for ( i = 0 ; i < NDECISIONS ; i++ )
+    { if ( RANDOMSTART > 0 )  // no priming, a random chain for every chromosome
+        { bug->brain.act[i].a = bug->brain.act[i].b = NULL;
+          for ( j = 0 ; j < RANDOMSTART ; j++ )
+            { bug->brain.act[i].a = random_gene( bug->brain.act[i].a );
+              bug->brain.act[i].b = random_gene( bug->brain.act[i].b );
+            }
+        }
+       else
+      switch ( i )
+        { case 0:
+          default:                 // shouldn't be used... except by BACKSTEP, which starts out tied with sleep and loses
+            bug->brain.act[i].a = add_gene( GENESENSE, THIRSTSENSE, -5000, 2500, NULL , 0 );
+            bug->brain.act[i].b = add_gene( GENESENSE, THIRSTSENSE,-20000, 3500, NULL , 0 );
+            break;
+
+          case 1:
+            bug->brain.act[i].a = add_gene( 5, SPAWNWEIGHTNORM, 1216, 1084, NULL               , 0 );
+            bug->brain.act[i].a = add_gene( 3, SPAWNWEIGHTNORM, 1216, 1084, bug->brain.act[i].a, 0 );
+            bug->brain.act[i].a = add_gene( GENECONST, NSENSECELLS + 1, 1500, 1048, bug->brain.act[i].a, 1 );
+            bug->brain.act[i].b = add_gene( 3, SPAWNWEIGHTNORM, 1203, 1056, NULL               , 0 );
+            bug->brain.act[i].b = add_gene( GENECONST, NSENSECELLS + 1, 2000, 1048, bug->brain.act[i].b, 1 );
+            break;
+
+          case 3:
+            bug->brain.act[i].a = add_gene( GENELIMIT, SENSESELF + i, 100, 1000, NULL, 0 );
+            bug->brain.act[i].b = add_gene( GENELIMIT, SENSESELF + i, 510,  514, NULL, 0 );
+            break;
+
+          case 2:
+            bug->brain.act[i].a = add_gene( GENELIMIT, SENSESELF + i,  50, 1200, NULL, 0 );
+            bug->brain.act[i].b = add_gene( GENELIMIT, SENSESELF + i, 760,  776, NULL, 0 );
+            break;
+
+          case 4:
+            bug->brain.act[i].a = add_gene( 3, STARVEWEIGHTNORM, 4274, 2187, NULL               , 0 );
+            bug->brain.act[i].a = add_gene( 3,  0,  173,  -53, bug->brain.act[i].a, 0 );
+            bug->brain.act[i].a = add_gene( GENECONST, NSENSECELLS + 1, 1500, 1048, bug->brain.act[i].a, 1 );
+            bug->brain.act[i].b = add_gene( 3, STARVEWEIGHTNORM, 3944, 2187, NULL               , 0 );
+            bug->brain.act[i].b = add_gene( 3,  0,  226,  -76, bug->brain.act[i].b, 0 );
+            bug->brain.act[i].b = add_gene( GENECONST, NSENSECELLS + 1, 2000, 1048, bug->brain.act[i].b, 1 );
+            break;
+
+          case 5:
+            bug->brain.act[i].a = add_gene( 2, 13,  734,  101, NULL               , 0 );
+            bug->brain.act[i].a = add_gene( 2, SENSESELF + ACTMATED, 1421,  456, bug->brain.act[i].a, 1 );
+            bug->brain.act[i].b = add_gene( 2, 13,  785,  101, NULL               , 0 );
+            bug->brain.act[i].b = add_gene( 2, SENSESELF + ACTMATED, 1339,  567, bug->brain.act[i].b, 1 );
+            break;
+
+          case 6:
+            bug->brain.act[i].a = add_gene( GENELIMIT, SPAWNWEIGHTNORM, 1200, 3000, NULL               , 1 );
+            bug->brain.act[i].a = add_gene( GENECONST, NSENSECELLS + 1, 3500, 1048, bug->brain.act[i].a, 1 );
+            bug->brain.act[i].b = add_gene( GENELIMIT, SPAWNWEIGHTNORM, 1800, 1850, NULL               , 1 );
+            bug->brain.act[i].b = add_gene( GENECONST, NSENSECELLS + 1, 4000, 1048, bug->brain.act[i].b, 1 );
+            break;
+
+          case RESPONSEMATE:
+            bug->brain.act[i].a = add_gene( 3, 11,  -50,  591, NULL               , 0 );
+            bug->brain.act[i].b = add_gene( 3, SENSESELF + ACTTURNCCW,  -79,  546, NULL               , 0 );
+            break;
+        }      
+      bug->brain.ngenes += countgenes( bug->brain.act[i].a );
+      bug->brain.ngenes += countgenes( bug->brain.act[i].b );
+      bug->brain.act[i].ea = bug->brain.eth;
+      bug->brain.act[i].eb = bug->brain.eth;
+      bug->matebrain.act[i].a = NULL;
+      bug->matebrain.act[i].b = NULL;
+    }
+
+  copy_brain( &(bug->brain), &(bug->matebrain) );  // also copies the ngenes and ndivide values
+  mutatebrain( &(bug->matebrain) );                // asexual reproduction always has a 50% chance of catching a mutation
+
+}
+
+//
+// Every IMMIGRATE days, while the population is under IMMIGRATEBELOW, a few bugs with
+// fresh random brains and a random color walk in at free cells - counted as births
+//
+void immigrate( void )
+{ struct _bugdata *bug;
+  struct     _pos  p;
+             long  i,j,k,tries;
+
+  if (( IMMIGRATE <= 0 ) || (( today % IMMIGRATE ) != 0 ) || ( buglist.n_bugs >= IMMIGRATEBELOW ))
+    return;
+
+  for ( k = 0 ; k < IMMIGRANTS ; k++ )
+    { tries = 0;
+      do
+        { p.x = limitedrandom( WORLD_X );
+          p.y = limitedrandom( WORLD_Y );
+        }
+      while (( world[p.x][p.y].bug != NULL ) && ( ++tries < 64 ));
+      if ( world[p.x][p.y].bug != NULL )  // the map is that full, nobody needs immigrants
+        continue;
+
+      bug = new_bug( &p, limitedrandom( 6 ) - 2, DIETHIN * 256, DIETHIN / 4 );
+      hist[today % LHIST].births++;
+      for ( i = 0 ; i < FAMHIST ; i++ )
+        { bug->brain.family[i].uid = -1;             // Unknown history
+          bug->brain.family[i].r   = ETHNIC_DUR / 8;
+          bug->brain.family[i].g   = ETHNIC_DUR / 8;
+          bug->brain.family[i].b   = ETHNIC_DUR / 8;
+        }
+      bug->brain.generation = 0;
+      bug->brain.divide     = 3;
+      if ( bug->brain.divide < DIVIDEMIN ) bug->brain.divide = DIVIDEMIN;
+      if ( bug->brain.divide > DIVIDEMAX ) bug->brain.divide = DIVIDEMAX;
+      bug->brain.eth.r = limitedrandom( ETHNIC_DUR + 1 );
+      bug->brain.eth.g = limitedrandom( ETHNIC_DUR + 1 );
+      bug->brain.eth.b = limitedrandom( ETHNIC_DUR + 1 );
+
+      bug->brain.ngenes = 0;
+      for ( i = 0 ; i < NDECISIONS ; i++ )
+        { bug->brain.act[i].a = bug->brain.act[i].b = NULL;
+          for ( j = 0 ; j < IMMIGRANTGENES ; j++ )
+            { bug->brain.act[i].a = random_gene( bug->brain.act[i].a );
+              bug->brain.act[i].b = random_gene( bug->brain.act[i].b );
+            }
+          bug->brain.ngenes += countgenes( bug->brain.act[i].a );
+          bug->brain.ngenes += countgenes( bug->brain.act[i].b );
+          bug->brain.act[i].ea = bug->brain.eth;
+          bug->brain.act[i].eb = bug->brain.eth;
+          bug->matebrain.act[i].a = NULL;
+          bug->matebrain.act[i].b = NULL;
+        }
+      bug->brain.expression = limitedrandom( 1 << NDECISIONS );
+
+      copy_brain( &(bug->brain), &(bug->matebrain) );
+      mutatebrain( &(bug->matebrain) );
+    }
+}
+
+//
+// Start the run over in place - all bugs freed, dynamic challenges back to their starting values, new seed
+// same as a fresh start with that seed, except that today's plots and reports overwrite the old run's files
+//
+void reset_sim( long seed )
+{ 
+  while ( buglist.n_bugs > 0 )
+    kill_bug( buglist.first );
+
+  leak         = -1;
+  geneknee     = GENEKNEE;
+  forcemate    = 0;
+  costmate     = COSTMATE;
+  agediv       = 0;
+  safety       = 1;
+  target_pop   = POP_TARGET;
+  drink_or_die = 4;
+  foodhump     = 1.4;
+  stage        = 0;
+  stagewait    = 0;
+  rngseed      = seed;
+  rngdraws     = 0;
+  stuckday     = 0;
+
+  init_world();
+  load_seed_image( SEEDIMAGE );
+  load_scenario( SCENARIO );
+  bug_one();
+}
+
+char *font_color( struct _ethnicity *e )
+{ static char str[40];
+
+  sprintf( str, "<font color=\"#%02x%02x%02x\">",(255 * ((short)e->r)) / ETHNIC_DUR
+                                                ,(255 * ((short)e->g)) / ETHNIC_DUR 
+                                                ,(255 * ((short)e->b)) / ETHNIC_DUR );
+  return str;
+}
+
+void chromosome_dump( FILE *fp, struct _gene *g )
+{ struct _gene *p;
+          long  i;
+
+  p = g;
+  i = 0;
+  while ( p != NULL )
+    { p->tp += i;      // markup for indexing
+      p = p->next;
+      i += 1024;
+    }
+
+  p = g;
+  while ( p != NULL )
+    { if ( ( (p->tp / 1024) % 4 == 0 ) && ( p->tp > 1536 ) ) fprintf( fp,"\r\n            " );
+      fprintf( fp, "[%2ld,%ld,%03ld,%6ld,%6ld,", p->tp / 1024,p->tp % 1024,p->si,p->c1,p->c2 );
+      if ( p->prod == NULL )
+        fprintf( fp, "p- ," );
+       else
+        fprintf( fp, "p%2ld,", p->prod->tp / 1024 );
+
+      if ( p->sum == NULL )
+        fprintf( fp, "s- ]" );
+       else
+        fprintf( fp, "s%2ld]", p->sum->tp / 1024 );
+      p = p->next;
+    }
+ 
+  p = g;
+  while ( p != NULL )
+    { p->tp = p->tp % 1024;      // markup for indexing
+      p = p->next;
+    }
+}
+
+//
+// Finding the bug that does the most with the least number of genes
+//
+long lean_genes( struct _bugdata *bp )
+{ long p;
+
+  // Age is an implied factor here
+  p = (1024 * bp->moves) / bp->brain.ngenes;
+  p = (p * (bp->mate_success + 1) ) / bp->brain.ngenes;
+  p = (p * bp->offspring * bp->offspring) / bp->brain.ngenes;
+
+  return p;
+}
+
+
+//
+// Complex formula for the "killer bug"
+//
+long slasher( struct _bugdata *bp )
+{ long age,offspring,moves,kills,defends,mates,p;
+
+  age       = today - bp->birthday;
+  offspring = bp->offspring;
+  moves     = bp->moves;
+  kills     = bp->kills;
+  defends   = bp->defends;
+  mates     = bp->mate_success;
+
+  p = kills * kills * kills * (offspring * 4 + mates + 1) * 1024;
+  p = p / (age * moves + 1024);
+
+  return p;
+}
+
+char *actname[NACT] = { "sleep", "eat", "turn cw", "turn ccw", "move", "mate", "divide",
+#if BACKSTEP
+                        "backstep",
+#endif
+                        "mated", "defend" };
+
+//
+// Score every decision on the bug's own senses where it stands, like bugdecide() but nothing is tallied
+// fills actv[] and returns the action the bug would take
+//
+long decision_values( struct _bugdata *bug, long *actv )
+{ long i,j,x;
+
+  gather_senses( bug );
+  j = 0;
+  x = 1;
+  for ( i = 0 ; i <= LASTACT ; i++ )
+    { if ( (bug->brain.expression & x) != 0 )
+        actv[i] = evaluate_gene( bug->brain.act[i].a );
+       else
+        actv[i] = evaluate_gene( bug->brain.act[i].b );
+      if ( actv[i] > actv[j] )
+        j = i;
+      x = x * 2;
+    }
+  return j;
+}
+
+//
+// What the bug would rather do where it stands now - every decision evaluated on its own senses, best first
+//
+void behavior_summary( FILE *fp, struct _bugdata *bug )
+{ long actv[NDECISIONS];
+  long used[NDECISIONS];
+  long i,j,k;
+
+  decision_values( bug, actv );
+  for ( i = 0 ; i <= LASTACT ; i++ )
+    used[i] = 0;
+
+  fprintf( fp, "Prefers" );
+  for ( k = 0 ; k <= LASTACT ; k++ )
+    { j = -1;
+      for ( i = 0 ; i <= LASTACT ; i++ )
+        if ( !used[i] && (( j < 0 ) || ( actv[i] > actv[j] )) )
+          j = i;
+      used[j] = 1;
+      fprintf( fp, "%s %s (%ld)", ( k == 0 ) ? "" : ( k == 1 ) ? ", then" : ",", actname[j], actv[j] );
+    }
+  fprintf( fp, "<br>\r\n" );
+}
+
+void bug_dump( FILE *fp, struct _bugdata *bug )
+{ long i,x;
+
+  if ( bug == NULL )
+    return;
+  fprintf( fp, "%sBug #%ld, generation %ld, %ld turns old, %ld genes, %ld mass, %ld hydration, [%ld,%ld] current pos<br>\r\n", font_color( &(bug->brain.eth) ), bug->brain.eth.uid, bug->brain.generation, today - bug->birthday, bug->brain.ngenes, bug->pos[0].weight / 1024, bug->pos[0].hydrate, bug->pos[0].p.x, bug->pos[0].p.y );
+  fprintf( fp, "%ld moves, %ld kills, %ld defs, %ld M+, %ld Mr, %ld M- (%ld Mx), %ld/(%ld) offs, %ld lean, %ld slasher<br>\r\n", bug->moves, bug->kills, bug->defends, bug->mate_success, bug->mate_repeat, bug->mate_fails, bug->mate_rejects, bug->offspring, bug->brain.divide, lean_genes( bug ), slasher( bug ) );
+  fprintf( fp, "Lifetime" );
+  for ( i = 0 ; i < NACT ; i++ )
+    fprintf( fp, "%s %s %ld", ( i == 0 ) ? "" : ",", actname[i], bug->actcount[i] );
+  fprintf( fp, "<br>\r\n" );
+  behavior_summary( fp, bug );
+
+  fprintf( fp, "<font size=-2><PRE>\r\nFamily History: \r\n" );
+  for ( i = 0 ; i < FAMHIST ; i++ )
+    { fprintf( fp, "%s%7ld</font> ", font_color( &(bug->brain.family[i]) ), bug->brain.family[i].uid );
+      if (( i ==  1 ) ||
+          ( i ==  5 ) ||
+          ( ((i - 13) % 16) == 0 ))
+        fprintf( fp,"\r\n" );
+    }
+  fprintf( fp, "\r\n" );
+
+  x = 1;
+
+  for ( i = 0 ; i < NDECISIONS ; i++ )
+    { fprintf (fp,"%s",font_color( &(bug->brain.act[i].ea) ) );
+      if ( (bug->brain.expression & x) != 0 )
+        fprintf( fp, "%2lda%8ld>", i, bug->brain.act[i].ea.uid );
+       else
+        fprintf( fp, "%2lda%8ld-", i, bug->brain.act[i].ea.uid );
+      chromosome_dump( fp, bug->brain.act[i].a );
+      fprintf (fp,"</font>%s",font_color( &(bug->brain.act[i].eb) ) );
+      if ( (bug->brain.expression & x) == 0 )
+        fprintf( fp, "\r\n%2ldb%8ld>", i, bug->brain.act[i].eb.uid );
+       else
+        fprintf( fp, "\r\n%2ldb%8ld-", i, bug->brain.act[i].eb.uid );
+      chromosome_dump( fp, bug->brain.act[i].b );
+      fprintf( fp, "</font>\r\n\r\n" );
+
+      x *= 2;
+    }
+  fprintf( fp, "</PRE></font></font>\r\n" );
+}
+
+//
+// One chromosome as Graphviz nodes and edges, prod links solid, sum links dashed
+// uses the same tp markup as chromosome_dump to number the genes
+//
+void chromosome_dot( FILE *fp, struct _gene *g, char *name )
+{ struct _gene *p;
+          long  i;
+
+  p = g;
+  i = 0;
+  while ( p != NULL )
+    { p->tp += i;      // markup for indexing
+      p = p->next;
+      i += 1024;
+    }
+
+  p = g;
+  while ( p != NULL )
+    { switch ( p->tp % 1024 )
+        { case GENECONST:   fprintf( fp, "    %s_%ld [label=\"const %ld\"];\n", name, p->tp / 1024, p->c1 ); break;
+          case GENESENSE:   fprintf( fp, "    %s_%ld [label=\"sense %ld\\n*%ld +%ld\", style=filled, fillcolor=lightblue];\n", name, p->tp / 1024, p->si, p->c1, p->c2 ); break;
+          case GENECOMPARE: fprintf( fp, "    %s_%ld [label=\"compare %ld\\n%ld +%ld\", style=filled, fillcolor=lightblue];\n", name, p->tp / 1024, p->si, p->c1, p->c2 ); break;
+          case GENEMATCH:   fprintf( fp, "    %s_%ld [label=\"match %ld\\n%ld *%ld\", style=filled, fillcolor=lightblue];\n", name, p->tp / 1024, p->si, p->c2, p->c1 ); break;
+          default:          fprintf( fp, "    %s_%ld [label=\"limit %ld\\n%ld..%ld\", style=filled, fillcolor=lightblue];\n", name, p->tp / 1024, p->si, p->c1, p->c2 ); break;
+        }
+      if ( p->prod != NULL )
+        fprintf( fp, "    %s_%ld -> %s_%ld [label=\"*\"];\n", name, p->tp / 1024, name, p->prod->tp / 1024 );
+      if ( p->sum != NULL )
+        fprintf( fp, "    %s_%ld -> %s_%ld [label=\"+\", style=dashed];\n", name, p->tp / 1024, name, p->sum->tp / 1024 );
+      p = p->next;
+    }
+
+  p = g;
+  while ( p != NULL )
+    { p->tp = p->tp % 1024;      // markup for indexing
+      p = p->next;
+    }
+}
+
+//
+// The whole brain as a Graphviz digraph, one cluster per chromosome, the expressed one in bold
+// render with: dot -Tpng bug.dot -o bug.png
+//
+void brain_dot( FILE *fp, struct _bugdata *bug )
+{ long i,x;
+  char name[16];
+
+  if ( bug == NULL )
+    return;
+  fprintf( fp, "digraph bug%ld {\n", bug->brain.eth.uid );
+  fprintf( fp, "  label=\"Bug #%ld, generation %ld, %d genes\";\n", bug->brain.eth.uid, bug->brain.generation, bug->brain.ngenes );
+  fprintf( fp, "  node [shape=box, fontsize=10];\n" );
+  x = 1;
+  for ( i = 0 ; i < NDECISIONS ; i++ )
+    { sprintf( name, "a%ld", i );
+      fprintf( fp, "  subgraph cluster_%s {\n    label=\"%s %s\";\n", name, actname[i], name );
+      if ( (bug->brain.expression & x) != 0 ) fprintf( fp, "    style=bold;\n" );
+      chromosome_dot( fp, bug->brain.act[i].a, name );
+      fprintf( fp, "  }\n" );
+
+      sprintf( name, "b%ld", i );
+      fprintf( fp, "  subgraph cluster_%s {\n    label=\"%s %s\";\n", name, actname[i], name );
+      if ( (bug->brain.expression & x) == 0 ) fprintf( fp, "    style=bold;\n" );
+      chromosome_dot( fp, bug->brain.act[i].b, name );
+      fprintf( fp, "  }\n" );
+      x *= 2;
+    }
+  fprintf( fp, "}\n" );
+}
+
+
+//
+// The daily history as a spreadsheet, as far back as hist[] reaches
+// on extinction the last day's history is never filled in, so it is left off
+//
+void write_stats( char *fname )
+{ FILE *fp;
+  long  d,i,last;
+
+  fp = fopen( fname, "wb" );
+  if ( fp == NULL )
+    return;
+  fprintf( fp, "day,bugs,avgweight,avgfood,avggenes,movement,collisions,starvations,drownings,births,red,green,blue,mixed\r\n" );
+  last = ( buglist.n_bugs > 0 ) ? today : today - 1;
+  for ( d = ( last >= LHIST ) ? last - LHIST + 1 : 1 ; d <= last ; d++ )
+    { fprintf( fp, "%ld,%ld,%ld,%ld,%ld,%ld,%ld,%ld,%ld,%ld", d, hist[d % LHIST].n_bugs, hist[d % LHIST].avgweight, hist[d % LHIST].avgfood, hist[d % LHIST].avggenes,
+               hist[d % LHIST].movement, hist[d % LHIST].collisions, hist[d % LHIST].starvations, hist[d % LHIST].drownings, hist[d % LHIST].births );
+      for ( i = 0 ; i < ETHBANDS ; i++ )
+        fprintf( fp, ",%ld", hist[d % LHIST].ethnic[i] );
+      fprintf( fp, "\r\n" );
+    }
+  fclose( fp );
+}
+
+//
+// stats.bin layout, all native byte order, so it can be memory mapped and plotted a column at a time:
+//   8 bytes "BUGSTAT1", long long days, long long fields, fields x 16 byte zero padded names,
+//   then fields columns of days long longs each, column f starts at 24 + 16*fields + 8*days*f
+//
+#define NSTATFIELDS ( 10 + ETHBANDS )
+char *statname[NSTATFIELDS] = { "day", "bugs", "avgweight", "avgfood", "avggenes", "movement", "collisions", "starvations", "drownings", "births",
+                                "red", "green", "blue", "mixed" };
+
+long stat_field( long d, long f )
+{ switch ( f )
+    { case 0: return d;
+      case 1: return hist[d % LHIST].n_bugs;
+      case 2: return hist[d % LHIST].avgweight;
+      case 3: return hist[d % LHIST].avgfood;
+      case 4: return hist[d % LHIST].avggenes;
+      case 5: return hist[d % LHIST].movement;
+      case 6: return hist[d % LHIST].collisions;
+      case 7: return hist[d % LHIST].starvations;
+      case 8: return hist[d % LHIST].drownings;
+      case 9: return hist[d % LHIST].births;
+    }
+  return hist[d % LHIST].ethnic[f - 10];
+}
+
+//
+// The same days as write_stats(), a column at a time
+//
+void write_stats_bin( char *fname )
+{      FILE *fp;
+       long  d,f,first,last;
+  long long  v;
+       char  name[16];
+
+  fp = fopen( fname, "wb" );
+  if ( fp == NULL )
+    return;
+  last  = ( buglist.n_bugs > 0 ) ? today : today - 1;
+  first = ( last >= LHIST ) ? last - LHIST + 1 : 1;
+  fwrite( "BUGSTAT1", 1, 8, fp );
+  v = ( last >= first ) ? last - first + 1 : 0;  fwrite( &v, sizeof( v ), 1, fp );
+  v = NSTATFIELDS;                               fwrite( &v, sizeof( v ), 1, fp );
+  for ( f = 0 ; f < NSTATFIELDS ; f++ )
+    { memset( name, 0, sizeof( name ) );
+      strncpy( name, statname[f], sizeof( name ) - 1 );
+      fwrite( name, 1, sizeof( name ), fp );
+    }
+  for ( f = 0 ; f < NSTATFIELDS ; f++ )
+    for ( d = first ; d <= last ; d++ )
+      { v = stat_field( d, f );
+        fwrite( &v, sizeof( v ), 1, fp );
+      }
+  fclose( fp );
+}
+
+//
+// One column of a stats.bin into out[], up to max days, returns the days read or -1 on a bad file
+//
+long read_stats_bin( char *fname, long field, long *out, long max )
+{      FILE *fp;
+       long  n;
+  long long  days,fields,v;
+       char  magic[8];
+
+  fp = fopen( fname, "rb" );
+  if ( fp == NULL )
+    return -1;
+  n = -1;
+  if (( fread( magic, 1, 8, fp ) == 8 ) && ( memcmp( magic, "BUGSTAT1", 8 ) == 0 ) &&
+      ( fread( &days, sizeof( days ), 1, fp ) == 1 ) && ( fread( &fields, sizeof( fields ), 1, fp ) == 1 ) &&
+      ( field >= 0 ) && ( field < fields ) &&
+      ( fseek( fp, 16 * fields + 8 * days * field, SEEK_CUR ) == 0 ))
+    { for ( n = 0 ; ( n < days ) && ( n < max ) ; n++ )
+        { if ( fread( &v, sizeof( v ), 1, fp ) != 1 )
+            break;
+          out[n] = v;
+        }
+    }
+  fclose( fp );
+  return n;
+}
+
+//
+// What made this run - version, seed and the switches that change the outcome - written once at the start
+// one "name value" pair per line, so it can be read back with fscanf() or compared with diff
+// every #define that changes what happens to the bugs is here, then one line per catastrophes[] entry
+//
+void write_run_info( char *fname )
+{ FILE *fp;
+  time_t now;
+  struct _catastrophe *c;
+
+  fp = fopen( fname, "wb" );
+  if ( fp == NULL )
+    return;
+  now = time( NULL );
+  fprintf( fp, "version %s\n",        VERSION );
+  fprintf( fp, "started %s",          ctime( &now ) );
+  fprintf( fp, "seed %d\n",           RNGSEED );  // restarts go on from RNGSEED + 1
+  fprintf( fp, "restarts %d\n",       RESTARTS );
+  fprintf( fp, "world %d %d\n",       WORLD_X, WORLD_Y );
+  fprintf( fp, "walled %d\n",         WALLED );
+  fprintf( fp, "seasonlength %d\n",   SEASONLENGTH );
+  fprintf( fp, "seedimage %s\n",      SEEDIMAGE );
+  fprintf( fp, "scenario %s\n",       SCENARIO );
+  fprintf( fp, "foodcap %d\n",        FOODCAP );
+  fprintf( fp, "foodhalflife %d\n",   FOODHALFLIFE );
+  fprintf( fp, "foodgrow %d\n",       FOODGROW );
+  fprintf( fp, "foodjitter %d\n",     FOODJITTER );
+  fprintf( fp, "foodshadow %d\n",     FOODSHADOW );
+  fprintf( fp, "foodspread %d\n",     FOODSPREAD );
+  fprintf( fp, "foodstart %d\n",      FOODSTART );
+  fprintf( fp, "foodnoise %d\n",      FOODNOISE );
+  fprintf( fp, "foodnoisecell %d\n",  FOODNOISECELL );
+  fprintf( fp, "fooddecay %d\n",      FOODDECAY );
+  fprintf( fp, "costsleep %d\n",      COSTSLEEP );
+  fprintf( fp, "sleeprecover %d\n",   SLEEPRECOVER );
+  fprintf( fp, "costeat %d\n",        COSTEAT );
+  fprintf( fp, "costturn %d\n",       COSTTURN );
+  fprintf( fp, "costmove %d\n",       COSTMOVE );
+  fprintf( fp, "costfight %d\n",      COSTFIGHT );
+  fprintf( fp, "costmate %d\n",       COSTMATE );
+  fprintf( fp, "costdivide %d\n",     COSTDIVIDE );
+  fprintf( fp, "dividemin %d\n",      DIVIDEMIN );
+  fprintf( fp, "dividemax %d\n",      DIVIDEMAX );
+  fprintf( fp, "dividemass %d\n",     DIVIDEMASS );
+  fprintf( fp, "spawnradius %d\n",    SPAWNRADIUS );
+  fprintf( fp, "parentshare %d\n",    PARENTSHARE );
+  fprintf( fp, "costcrowd %d\n",      COSTCROWD );
+  fprintf( fp, "crowdradius %d\n",    CROWDRADIUS );
+  fprintf( fp, "crowdlimit %d\n",     CROWDLIMIT );
+  fprintf( fp, "nommass %d\n",        NOMMASS );
+  fprintf( fp, "genecost %d\n",       GENECOST );
+  fprintf( fp, "geneknee %d\n",       GENEKNEE );
+  fprintf( fp, "geneexp %d\n",        GENEEXP );
+  fprintf( fp, "squash %d\n",         DECISIONSQUASH );
+  fprintf( fp, "randomstart %d\n",    RANDOMSTART );
+  fprintf( fp, "randomface %d\n",     RANDOMFACE );
+  fprintf( fp, "immigrate %d\n",      IMMIGRATE );
+  fprintf( fp, "immigratebelow %d\n", IMMIGRATEBELOW );
+  fprintf( fp, "immigrants %d\n",     IMMIGRANTS );
+  fprintf( fp, "immigrantgenes %d\n", IMMIGRANTGENES );
+  fprintf( fp, "freezegenes %d\n",    FREEZEGENES );
+  fprintf( fp, "eatlimit %d\n",       EATLIMIT );
+  fprintf( fp, "eatreach %d\n",       EATREACH );
+  fprintf( fp, "eatefficiency %d\n",  EATEFFICIENCY );
+  fprintf( fp, "diethin %d\n",        DIETHIN );
+  fprintf( fp, "masscap %d\n",        MASSCAP );
+  fprintf( fp, "masslimit %d\n",      MASSLIMIT );
+  fprintf( fp, "masstarget %.1f\n",   MASSTARGET );
+  fprintf( fp, "backstep %d\n",       BACKSTEP );
+  fprintf( fp, "actionsperday %d\n",  ACTIONSPERDAY );
+  fprintf( fp, "poshistory %d\n",     POSHISTORY );
+  fprintf( fp, "acthistory %d\n",     ACTHISTORY );
+  fprintf( fp, "scent %d\n",          SCENT );
+  fprintf( fp, "scentdeposit %d\n",   SCENTDEPOSIT );
+  fprintf( fp, "scentdecay %d\n",     SCENTDECAY );
+  fprintf( fp, "scentspread %d\n",    SCENTSPREAD );
+  fprintf( fp, "senseradius %d\n",    SENSERADIUS );
+  fprintf( fp, "poppressure %d\n",    POPPRESSURE );
+  fprintf( fp, "senses %d\n",         NSENSES );
+  fprintf( fp, "famhist %d\n",        FAMHIST );
+  fprintf( fp, "ethnicdur %d\n",      ETHNIC_DUR );
+  fprintf( fp, "drowntime %d\n",      DROWN_TIME );
+  fprintf( fp, "stuckwindow %d\n",    STUCKWINDOW );
+  fprintf( fp, "stuckstop %d\n",      STUCKSTOP );
+  for ( c = catastrophes ; c->day > 0 ; c++ )  // day kind x1 y1 x2 y2 amount
+    fprintf( fp, "catastrophe %ld %ld %ld %ld %ld %ld %ld\n", c->day, c->kind, c->x1, c->y1, c->x2, c->y2, c->amount );
+  fclose( fp );
+}
+
+//
+// The fields of a gene that evaluate_gene() actually uses, so genes that act the same compare the same
+// a constant ignores si and c2, a compare falls through into the match code, a match reads c2 % NSENSES
+//
+void gene_canon( struct _gene *g, struct _gene *c )
+{ *c = *g;
+  if (( c->tp < GENECONST ) || ( c->tp > GENEMATCH ))
+    c->tp = GENELIMIT;   // the switch default
+  if ( c->tp == GENECOMPARE )
+    c->tp = GENEMATCH;
+  if ( c->tp == GENECONST )
+    c->si = c->c2 = 0;
+  if ( c->tp == GENEMATCH )
+    c->c2 = c->c2 % NSENSES;
+}
+
+//
+// How different two genes are, 0 = same, 1024 = nothing alike
+//
+long gene_distance( struct _gene *g1, struct _gene *g2 )
+{ struct _gene c1,c2;
+          long d;
+
+  gene_canon( g1, &c1 );
+  gene_canon( g2, &c2 );
+  if ( c1.tp != c2.tp )
+    return 1024;
+  d = 0;
+  if ( c1.si != c2.si )
+    d += 512;
+  d += ( myabs( c1.c1 - c2.c1 ) + myabs( c1.c2 - c2.c2 ) ) / 8;
+  if ( d > 1024 )
+    d = 1024;
+  return d;
+}
+
+//
+// Genome distance - the chromosomes' gene lists are compared in order, a gene with no partner counts 1024
+//
+long genome_distance( struct _bugbrain *b1, struct _bugbrain *b2 )
+{ struct _gene *g1,*g2;
+          long  i,j,d;
+
+  d = 0;
+  for ( i = 0 ; i < NDECISIONS ; i++ )
+    for ( j = 0 ; j < 2 ; j++ )
+      { g1 = ( j == 0 ) ? b1->act[i].a : b1->act[i].b;
+        g2 = ( j == 0 ) ? b2->act[i].a : b2->act[i].b;
+        while (( g1 != NULL ) || ( g2 != NULL ))
+          { if (( g1 == NULL ) || ( g2 == NULL ))
+              d += 1024;
+             else
+              d += gene_distance( g1, g2 );
+            if ( g1 != NULL ) g1 = g1->next;
+            if ( g2 != NULL ) g2 = g2->next;
+          }
+      }
+  return d;
+}
+
+//
+// Greedy clustering - each bug joins the first cluster whose founding bug is within CLUSTERDIST, or founds a new one
+// once GENOMECLUSTERS are founded, the rest are counted as unclustered.  Listed largest first.
+//
+void genome_clusters( FILE *fp )
+{ struct _bugdata *rep[GENOMECLUSTERS+1];
+  struct _bugdata *bp;
+             long  size[GENOMECLUSTERS+1];
+             long  n,i,j,other;
+
+  n = other = 0;
+  for ( bp = buglist.first ; bp != NULL ; bp = bp->next )
+    { for ( i = 0 ; i < n ; i++ )
+        if ( genome_distance( &(bp->brain), &(rep[i]->brain) ) < CLUSTERDIST )
+          break;
+      if ( i < n )
+        size[i]++;
+       else if ( n < GENOMECLUSTERS )
+        { rep[n]  = bp;
+          size[n] = 1;
+          n++;
+        }
+       else
+        other++;
+    }
+
+  for ( i = 0 ; i < n ; i++ )  // largest first
+    for ( j = i + 1 ; j < n ; j++ )
+      if ( size[j] > size[i] )
+        { size[GENOMECLUSTERS] = size[i]; size[i] = size[j]; size[j] = size[GENOMECLUSTERS];
+          rep[GENOMECLUSTERS]  = rep[i];  rep[i]  = rep[j];  rep[j]  = rep[GENOMECLUSTERS];
+        }
+
+  fprintf( fp, "<br>Genome clusters, distance under %d:<br>\r\n", CLUSTERDIST );
+  for ( i = 0 ; i < n ; i++ )
+    fprintf( fp, "%ld bugs like %s#%ld</font>, %d genes<br>\r\n", size[i], font_color( &(rep[i]->brain.eth) ), rep[i]->brain.eth.uid, rep[i]->brain.ngenes );
+  if ( other > 0 )
+    fprintf( fp, "%ld bugs in no cluster<br>\r\n", other );
+}
+
+void bug_report( char *fname, char *iname )
+{ struct _bugdata *bp;
+  struct _bugdata *bm;
+             FILE *fp;
+             long  genesum[NDECISIONS];
+             long  i,x,y;
+
+  fp = fopen( fname, "wb");
+  
+  fprintf( fp,"<HTML><HEAD><TITLE>Bug Report Year %6.2f</TITLE></HEAD><BODY TEXT=\"#C0C0C0\" BGCOLOR=\"#000000\">\r\n", 
+         ((float)today)/((float)SEASONLENGTH) );
+
+  fprintf( fp,"<CENTER><H1>Bug Report</H1><H2>Year %6.2f</H2><img src=\"%s\"><br></CENTER>\r\n",
+         ((float)today)/((float)SEASONLENGTH), iname );
+
+  fprintf( fp, "%ld Days elapsed<br>%ld Bugs living, %4.1f%% space consumed<br>%ld bugs born throughout history<br>%5.0f Food per cell, on average<br>%5.0f mass of average bug<br>%ld target population<br>%6.2f genes in average bug:<br>\r\n", 
+                today, buglist.n_bugs, ((float)(buglist.n_bugs * 100))/((float)(WORLD_X * WORLD_Y)), idcounter, (float)totalfood/((float)WORLD_X * WORLD_Y), (float)totalbug/((float)buglist.n_bugs*1024),target_pop,((float)genecount)/((float)buglist.n_bugs) ); 
+
+  for ( i = 0 ; i < NDECISIONS ; i++ )
+    genesum[i] = 0;
+  for ( x = 0 ; x < WORLD_X ; x++ )
+    for ( y = 0 ; y < WORLD_Y ; y++ )
+      { if ( world[x][y].bug != NULL )
+          for ( i = 0 ; i < NDECISIONS ; i++ )
+            genesum[i] += countgenes( world[x][y].bug->brain.act[i].a ) + 
+                          countgenes( world[x][y].bug->brain.act[i].b );
+      }
+  y = 0;
+  for ( i = 0 ; i < NDECISIONS ; i++ )
+    y += genesum[i];
+  for ( i = 0 ; i < NDECISIONS ; i++ )
+    fprintf( fp, "%4.1f%% in chromosome %ld<br>",((float)(100 * genesum[i]))/((float)y), i );
+
+  fprintf( fp, "%4ld Gene Knee, cost exponent %d<br>", geneknee, GENEEXP );
+  fprintf( fp, "%3ld Min Age of Division, materule: %02lx food factor %5.3f<br>", agediv, forcemate, foodhump );
+  fprintf( fp, "%ld random draws, seed now %ld<br>", rngdraws, rngseed );
+  if ( stuckday > 0 )
+    fprintf( fp, "Stuck since day %ld, nothing changed for the %d days before<br>", stuckday, STUCKWINDOW );
+
+  fprintf( fp, "<br>Lifespan at death (turns: bugs):<br>%ld: %ld", 0L, lifespan[0] );
+  for ( i = 1 ; i < LIFEBINS ; i++ )
+    fprintf( fp, ", %ld%s: %ld", 1L << (i-1), ( i < LIFEBINS - 1 ) ? "" : "+", lifespan[i] );
+  fprintf( fp, "<br>\r\n" );
+
+  fprintf( fp, "<br>Ethnic bands, bugs (red, green, blue, mixed):<br>\r\n" );
+  for ( x = ( today > LHIST - 100 ) ? today - (LHIST - 100) : 0 ; x <= today ; x++ )  // every 100 days back through the history
+    if (( x % 100 == 0 ) || ( x == today ))
+      { fprintf( fp, "%ld:", x );
+        for ( i = 0 ; i < ETHBANDS ; i++ )
+          fprintf( fp, " %ld", hist[x % LHIST].ethnic[i] );
+        fprintf( fp, "<br>\r\n" );
+      }
+
+  if ( GENOMECLUSTERS > 0 )
+    genome_clusters( fp );
+
+  if ( DECPROFILE && ( decn > 0 ) )
+    { fprintf( fp, "<br>Decision profile, %ld decisions (chosen, average output):<br>", decn );
+      for ( i = 0 ; i <= LASTACT ; i++ )
+        fprintf( fp, "%ld: %4.1f%% %ld<br>", i, ((float)(100 * decwins[i]))/((float)decn), decsum[i] / decn );
+      for ( i = 0 ; i < NDECISIONS ; i++ )
+        decwins[i] = decsum[i] = 0;
+      decn = 0;
+    }
+
+  fprintf( fp, "<br>Exceptional bug reports:<br><br>" );
+
+
+  fprintf( fp, "Oldest\r\n" );
+  bug_dump( fp, buglist.first );
+
+  fprintf( fp, "Newest\r\n" );
+  bug_dump( fp, buglist.endlist );
+
+  fprintf( fp, "Median\r\n" );
+  bp = buglist.first;
+  i = buglist.n_bugs / 2;
+  while ( i > 0 )
+    { bp = bp->next;
+      i--;
+    }
+  bug_dump( fp, bp );
+  
+
+  fprintf( fp, "Most kills\r\n" );
+  bp = bm = buglist.first;
+  while ( bp != NULL )
+    { if ( bp->kills > bm->kills )
+        bm = bp;
+       bp = bp->next;
+    }
+  bug_dump( fp, bm ); 
+
+  fprintf( fp, "Most moves\r\n" );
+  bp = bm = buglist.first;
+  while ( bp != NULL )
+    { if ( bp->moves > bm->moves )
+        bm = bp;
+       bp = bp->next;
+    }
+  bug_dump( fp, bm ); 
+
+  fprintf( fp, "Most defends\r\n" );
+  bp = bm = buglist.first;
+  while ( bp != NULL )
+    { if ( bp->defends > bm->defends )
+        bm = bp;
+       bp = bp->next;
+    }
+  bug_dump( fp, bm ); 
+
+  fprintf( fp, "Most offspring\r\n" );
+  bp = bm = buglist.first;
+  while ( bp != NULL )
+    { if ( bp->offspring > bm->offspring )
+        bm = bp;
+       bp = bp->next;
+    }
+  bug_dump( fp, bm ); 
+
+  fprintf( fp, "Lowest generation\r\n" );
+  bp = bm = buglist.first;
+  while ( bp != NULL )
+    { if ( bp->brain.generation < bm->brain.generation )
+        bm = bp;
+       bp = bp->next;
+    }
+  bug_dump( fp, bm ); 
+
+  fprintf( fp, "Highest generation\r\n" );
+  bp = bm = buglist.first;
+  while ( bp != NULL )
+    { if ( bp->brain.generation > bm->brain.generation )
+        bm = bp;
+       bp = bp->next;
+    }
+  bug_dump( fp, bm ); 
+
+  fprintf( fp, "Least genes\r\n" );
+  bp = bm = buglist.first;
+  while ( bp != NULL )
+    { if ( bp->brain.ngenes < bm->brain.ngenes )
+        bm = bp;
+       bp = bp->next;
+    }
+  bug_dump( fp, bm ); 
+
+  fprintf( fp, "Most genes\r\n" );
+  bp = bm = buglist.first;
+  while ( bp != NULL )
+    { if ( bp->brain.ngenes > bm->brain.ngenes )
+        bm = bp;
+       bp = bp->next;
+    }
+  bug_dump( fp, bm ); 
+
+  fprintf( fp, "Heaviest\r\n" );
+  bp = bm = buglist.first;
+  while ( bp != NULL )
+    { if ( bp->pos[0].weight > bm->pos[0].weight )
+        bm = bp;
+       bp = bp->next;
+    }
+  bug_dump( fp, bm ); 
+
+  fprintf( fp, "Lean Genes\r\n" );
+  bp = bm = buglist.first;
+  while ( bp != NULL )
+    { if ( lean_genes( bp ) > lean_genes( bm ) )
+        bm = bp;
+       bp = bp->next;
+    }
+  bug_dump( fp, bm ); 
+
+  fprintf( fp, "Slasher Prize\r\n" );
+  bp = bm = buglist.first;
+  while ( bp != NULL )
+    { if ( slasher( bp ) > slasher( bm ) )
+        bm = bp;
+       bp = bp->next;
+    }
+  bug_dump( fp, bm ); 
+
+  fprintf( fp,"</BODY></HTML>\r\n");
+
+  fclose( fp );
+}
+
+//
+// Is the bug uid, or is uid among its ancestors?  Only as far back as the family history reaches.
+//
+long in_lineage( struct _bugbrain *b, long uid )
+{ long i;
+
+  if ( b->eth.uid == uid )
+    return 1;
+  for ( i = 0 ; i < FAMHIST ; i++ )
+    if ( b->family[i].uid == uid )
+      return 1;
+  return 0;
+}
+
+//
+// Color of each action in the RIGHTBAR activity plot
+//
+long act_color( long act )
+{ switch ( act )
+    { case ACTSLEEP:   return gdTrueColor(   0,  0,255 );  // blue
+      case ACTEAT:     return gdTrueColor(   0,255,  0 );  // green
+      case ACTTURNCW:  return gdTrueColor( 128,128,  0 );  // orange-brown
+      case ACTTURNCCW: return gdTrueColor( 128,  0,128 );  // purple
+      case ACTMOVE:    return gdTrueColor( 255,  0,  0 );  // red
+      case ACTMATE:    return gdTrueColor( 255,255,255 );  // white
+      case ACTDIVIDE:  return gdTrueColor(   0,255,255 );  // cyan
+#if BACKSTEP
+      case ACTBACKSTEP:return gdTrueColor( 255,128,  0 );  // orange
+#endif
+      case ACTMATED:   return gdTrueColor( 128,  0,255 );  // purple-blue
+      case ACTDEFEND:  return gdTrueColor( 192,255,  0 );  // yellow-green
+    }
+  return gdTrueColor( 255,255,255 );
+}
+
+//
+// A fully saturated color for hue h, 0 to 1023 around the wheel, 0 is red
+//
+long hue_color( long h )
+{ long f;
+
+  h = h % 1024;
+  f = ((h % 171) * 255) / 171;  // six sectors of about 171
+  switch ( h / 171 )
+    { case 0: return gdTrueColor( 255, f, 0 );
+      case 1: return gdTrueColor( 255 - f, 255, 0 );
+      case 2: return gdTrueColor( 0, 255, f );
+      case 3: return gdTrueColor( 0, 255 - f, 255 );
+      case 4: return gdTrueColor( f, 0, 255 );
+    }
+  return gdTrueColor( 255, 0, 255 - f );
+}
+
+//
+// A bug's color on the bug map for the COLORBY schemes other than ethnicity, i is the position history index
+//
+long bug_color( struct _bugdata *bug, long i )
+{ long a;
+
+  switch ( COLORBY )
+    { case COLORID:   // 633/1024 of a turn per uid, the golden ratio, neighbors in the same family still look different
+        return hue_color( (bug->brain.eth.uid * 633) % 1024 );
+
+      case COLORAGE:  // yellow newborns turning red, like the env map, by about 1000 turns old
+        a = 255 - (today - bug->birthday) / 4; if ( a < 0 ) a = 0;
+        return gdTrueColor( 255,a,0 );
+
+      case COLORACT:
+        return act_color( bug->pos[i].act );
+    }
+  return gdTrueColor( 255,255,255 );
+}
+
+//
+// Key to the action colors, one swatch and name per action, in the corner under the LEFTBAR
+//
+void draw_legend( gdImagePtr im )
+{ long i,y;
+
+  gdImageFilledRectangle( im, 0, WORLD_Y, LEFTBAR - 1, WORLD_Y + BOTTOMBAR - 1, gdTrueColor( 0,0,0 ) );
+  for ( i = 0 ; i < NACT ; i++ )
+    { y = WORLD_Y + ( i * BOTTOMBAR ) / NACT;
+      gdImageFilledRectangle( im, 1, y + 1, 6, y + 6, act_color( i ) );
+      gdImageString( im, gdFontGetTiny(), 9, y, (unsigned char *)actname[i], gdTrueColor( 192,192,192 ) );
+    }
+}
+
+//
+// Key to the environment map, one color scale per channel from its low end on the left, in the corner under the LEFTBAR
+//
+void draw_env_legend( gdImagePtr im )
+{ long i,x,y,v,c;
+  char *name[4] = { "bug age", "food", "water", "height" };
+
+  gdImageFilledRectangle( im, 0, WORLD_Y, LEFTBAR - 1, WORLD_Y + BOTTOMBAR - 1, gdTrueColor( 0,0,0 ) );
+  for ( i = 0 ; i < (( ENVTERRAIN > 0 ) ? 4 : 3 ) ; i++ )
+    { y = WORLD_Y + 2 + i * 12;
+      for ( x = 0 ; x < 32 ; x++ )
+        { switch ( i )
+            { case 0:  c = gdTrueColor( 255 - x * 4,0,0 ); break;   // newborn to 512 days and older
+              case 1:  c = gdTrueColor( 0,x * 6,0 );       break;   // bare to FOODCAP
+              case 2:  c = gdTrueColor( 0,0,64 + x * 6 );  break;   // the first water to 191 and deeper
+              default: v = ( x * ENVTERRAIN ) / 31; if ( v > 255 ) v = 255;
+                       c = gdTrueColor( v,v,v );          break;   // deepest pit to highest hill
+            }
+          gdImageLine( im, 1 + x, y, 1 + x, y + 7, c );
+        }
+      gdImageString( im, gdFontGetTiny(), 36, y, (unsigned char *)name[i], gdTrueColor( 192,192,192 ) );
+    }
+}
+
+//
+// Write a plot to an open file, JPEG at high quality, or a GIF through a 256 color copy when GIFOUT is set
+// the maps are mostly flat colors, only the fading position trails and graph lines get rounded to the palette
+//
+void save_image( gdImagePtr im, FILE *fp )
+{ gdImagePtr pal;
+
+  if ( GIFOUT )
+    { pal = gdImageCreatePaletteFromTrueColor( im, 0, 256 );
+      gdImageGif( pal, fp );
+      gdImageDestroy( pal );
+    }
+   else
+    gdImageJpeg( im, fp, 95 ); //write the image to the file using high quality setting
+}
+
+void image_plot( gdImagePtr im, gdImagePtr imout, char *fn )
+{           long  x,y,i,r,g,b,c,bugs,age,mass,kills,genes,maxbugs,maxage,maxkills,maxbd,maxmass,maxgenes,minmass,mingenes;
+            long  lastage,lastbugs,lastmass,lastkills,lastgenes,poppct,maxelev;
+            long  actsum[NACT];
+            long  score[NDECISIONS];
+            FILE *jpegout; //output file
+ struct _bugdata *bug;
+
+          c = gdTrueColor( 0,0,0 );
+          gdImageFilledRectangle(im, 0, 0, WORLD_X + SIDEBAR, WORLD_Y + BOTTOMBAR, c); // Erase image
+
+
+          if ( fn[0] == 'b' )
+            {
+// Plot the bug map
+          for ( i = POSHISTORY-1 ; i >= 0 ; i-- )
+            { bug = buglist.first;
+              while ( bug != NULL )
+                { if ( COLORBY == COLORETH )
+                    { r = (255 * (((short)bug->brain.eth.r) * ( POSHISTORY - i )) / POSHISTORY) / ETHNIC_DUR;
+                      g = (255 * (((short)bug->brain.eth.g) * ( POSHISTORY - i )) / POSHISTORY) / ETHNIC_DUR;
+                      b = (255 * (((short)bug->brain.eth.b) * ( POSHISTORY - i )) / POSHISTORY) / ETHNIC_DUR;
+                    }
+                   else  // same fade along the trail
+                    { c = bug_color( bug, i );
+                      r = (gdTrueColorGetRed(   c ) * ( POSHISTORY - i )) / POSHISTORY;
+                      g = (gdTrueColorGetGreen( c ) * ( POSHISTORY - i )) / POSHISTORY;
+                      b = (gdTrueColorGetBlue(  c ) * ( POSHISTORY - i )) / POSHISTORY;
+                    }
+                  if (( TRACKLINEAGE >= 0 ) && !in_lineage( &(bug->brain), TRACKLINEAGE ))
+                    { r /= 4; g /= 4; b /= 4; }  // not family, fade into the background
+                  c = gdTrueColor( r,g,b );
+                  gdImageSetPixel( im,LEFTBAR + bug->pos[i].p.x,bug->pos[i].p.y,c );
+                  bug = bug->next;
+                }
+            }
+            }
+           else if ( fn[0] == 'p' )
+            {
+// Plot the phenotype map - each bug in the color of the action it would take where it stands
+          gdImageFilledRectangle( im, LEFTBAR, 0, LEFTBAR + WORLD_X - 1, WORLD_Y - 1, gdTrueColor( 0,0,0 ) );  // empty cells stay black
+          for ( bug = buglist.first ; bug != NULL ; bug = bug->next )
+            gdImageSetPixel( im,LEFTBAR + bug->pos[0].p.x,bug->pos[0].p.y,act_color( decision_values( bug, score ) ) );
+            }
+           else
+            {
+// Plot the environment map
+          maxelev = 1;  // the land starts level at 0
+          if ( ENVTERRAIN > 0 )
+            for ( x = 0 ; x < WORLD_X ; x++ )
+              for ( y = 0 ; y < WORLD_Y ; y++ )
+                if ( myabs( world[x][y].elevation ) > maxelev ) 
+                  maxelev = myabs( world[x][y].elevation );
+          for ( x = 0 ; x < WORLD_X ; x++ )
+            for ( y = 0 ; y < WORLD_Y ; y++ )
+               { 
+                 if ( world[x][y].bug != NULL ) 
+                   { r  = 255 + (world[x][y].bug->birthday - today) / 4; if ( r < 128 ) r = 128;
+                   }
+                  else
+                   { r = 0; }
+                 g = ( world[x][y].food * 192 ) / FOODCAP; if ( g > 255 ) g = 255;
+		 if ( world[x][y].water == 0 ) b = 0;
+		   else 
+		    { b = world[x][y].water + 64;
+		      if ( b > 255 ) b = 255;
+		    }
+                 if ( ENVTERRAIN > 0 )  // terrain shows through as gray
+                   { i = ENVTERRAIN / 2 + ( world[x][y].elevation * ENVTERRAIN ) / ( maxelev * 2 );
+                     r += i; if ( r > 255 ) r = 255;
+                     g += i; if ( g > 255 ) g = 255;
+                     b += i; if ( b > 255 ) b = 255;
+                   }
+                 c = gdTrueColor( r,g,b );
+                 gdImageSetPixel( im,x + LEFTBAR,y,c );
+               }
+            }
+
+          // Draw the bottom graph
+          // do the autoranging
+          maxbugs  = maxbd = maxmass = maxgenes = 1;
+          minmass  = 0; // hist[today % LHIST].avgweight;
+          mingenes = hist[today % LHIST].avggenes;
+          y = WORLD_X + SIDEBAR;
+          if ( y > today )
+            y = today;
+          for ( x = 0 ; x < y ; x++ )
+            { if ( hist[(today - x) % LHIST].n_bugs > maxbugs )
+                maxbugs = hist[(today - x) % LHIST].n_bugs;
+
+              poppct = (1024 * hist[(today - x) % LHIST].n_bugs) / (WORLD_X  * WORLD_Y);
+
+              if ( hist[(today - x) % LHIST].births > maxbd )  // births, starve+collide, and movement
+                maxbd = hist[(today - x) % LHIST].births;      // all plot on the same scale
+              if ( hist[(today - x) % LHIST].collisions + 
+                   hist[(today - x) % LHIST].drownings + 
+                   hist[(today - x) % LHIST].starvations > maxbd )
+                maxbd = hist[(today - x) % LHIST].collisions
+                      + hist[(today - x) % LHIST].starvations
+		      + hist[(today - x) % LHIST].drownings;
+              if ( hist[(today - x) % LHIST].movement * poppct / 1024 > maxbd ) 
+                maxbd = hist[(today - x) % LHIST].movement * poppct / 1024;
+
+              if ( hist[(today - x) % LHIST].avgweight > maxmass )
+                maxmass = hist[(today - x) % LHIST].avgweight;
+              if ( hist[(today - x) % LHIST].avgfood > maxmass )
+                maxmass = hist[(today - x) % LHIST].avgfood;
+
+              if ( hist[(today - x) % LHIST].avgweight < minmass )
+                minmass = hist[(today - x) % LHIST].avgweight;
+              // if ( hist[(today - x) % LHIST].avgfood < minmass )
+              //   minmass = hist[(today - x) % LHIST].avgfood;
+
+              if ( hist[(today - x) % LHIST].avggenes > maxgenes )
+                maxgenes = hist[(today - x) % LHIST].avggenes;
+              if ( hist[(today - x) % LHIST].avggenes < mingenes )
+                mingenes = hist[(today - x) % LHIST].avggenes;
+            }
+          if ( maxgenes == mingenes ) maxgenes++;
+          if ( maxmass == minmass ) maxmass++;
+          if ( maxbd == 0 ) maxbd++;
+          // Plot the graph
+          for ( x = 0 ; x < y ; x++ )
+            { c = gdTrueColor( 255,255,255 );  // nbugs forms a white background
+              gdImageLine( im, WORLD_X + SIDEBAR - 1 - x, WORLD_Y + BOTTOMBAR - 1, 
+                               WORLD_X + SIDEBAR - 1 - x, WORLD_Y + BOTTOMBAR - 1 - (hist[(today - x) % LHIST].n_bugs * BOTTOMBAR) / maxbugs, c );
+              if ( x > 0 )
+                { c = gdTrueColor( 96,96,96 );  // genes are grey
+                  gdImageLine( im, WORLD_X + SIDEBAR - x    , WORLD_Y + BOTTOMBAR - 1 - ((hist[(today - x + 1) % LHIST].avggenes - mingenes) * BOTTOMBAR) / (maxgenes - mingenes),
+                                   WORLD_X + SIDEBAR - 1 - x, WORLD_Y + BOTTOMBAR - 1 - ((hist[(today - x    ) % LHIST].avggenes - mingenes) * BOTTOMBAR) / (maxgenes - mingenes), c );
+
+                  c = gdTrueColor( 0, 255, 0 );  // food is green
+                  gdImageLine( im, WORLD_X + SIDEBAR - x    , WORLD_Y + BOTTOMBAR - 1 - ((hist[(today - x + 1) % LHIST].avgfood - minmass) * BOTTOMBAR) / (maxmass - minmass),
+                                   WORLD_X + SIDEBAR - 1 - x, WORLD_Y + BOTTOMBAR - 1 - ((hist[(today - x    ) % LHIST].avgfood - minmass) * BOTTOMBAR) / (maxmass - minmass), c );
+
+                  c = gdTrueColor( 0, 0, 255 );  // bugs are blue
+                  gdImageLine( im, WORLD_X + SIDEBAR - x    , WORLD_Y + BOTTOMBAR - 1 - ((hist[(today - x + 1) % LHIST].avgweight - minmass) * BOTTOMBAR) / (maxmass - minmass),
+                                   WORLD_X + SIDEBAR - 1 - x, WORLD_Y + BOTTOMBAR - 1 - ((hist[(today - x    ) % LHIST].avgweight - minmass) * BOTTOMBAR) / (maxmass - minmass), c );
+
+                  poppct = (1024 * hist[(today - x) % LHIST].n_bugs) / (WORLD_X  * WORLD_Y);
+
+                  c = gdTrueColor( 0,255,128 );  // movement is bright green
+                  gdImageLine( im, WORLD_X + SIDEBAR - x    , WORLD_Y + BOTTOMBAR - 1 - ((hist[(today - x + 1) % LHIST].movement * poppct / 1024) * BOTTOMBAR) / maxbd,
+                                   WORLD_X + SIDEBAR - 1 - x, WORLD_Y + BOTTOMBAR - 1 - ((hist[(today - x    ) % LHIST].movement * poppct / 1024) * BOTTOMBAR) / maxbd, c );
+
+                  c = gdTrueColor( 0,128,0 );  // starvations are dk green, plotted first so may be overwritten, and plotted on top of collisions as a total death measure
+                  gdImageLine( im, WORLD_X + SIDEBAR - x    , WORLD_Y + BOTTOMBAR - 1 - ((hist[(today - x + 1) % LHIST].collisions + hist[(today - x + 1) % LHIST].starvations) * BOTTOMBAR) / maxbd,
+                                   WORLD_X + SIDEBAR - 1 - x, WORLD_Y + BOTTOMBAR - 1 - ((hist[(today - x    ) % LHIST].collisions + hist[(today - x    ) % LHIST].starvations) * BOTTOMBAR) / maxbd, c );
+
+                  c = gdTrueColor( 64,0,192 );  // drownings are purply
+                  gdImageLine( im, WORLD_X + SIDEBAR - x    , WORLD_Y + BOTTOMBAR - 1 - (hist[(today - x + 1) % LHIST].drownings * BOTTOMBAR) / maxbd,
+                                   WORLD_X + SIDEBAR - 1 - x, WORLD_Y + BOTTOMBAR - 1 - (hist[(today - x    ) % LHIST].drownings * BOTTOMBAR) / maxbd, c );
+                  
+		  c = gdTrueColor( 255,0,0 );  // collisions are red
+                  gdImageLine( im, WORLD_X + SIDEBAR - x    , WORLD_Y + BOTTOMBAR - 1 - ((hist[(today - x + 1) % LHIST].drownings + hist[(today - x + 1) % LHIST].collisions) * BOTTOMBAR) / maxbd,
+                                   WORLD_X + SIDEBAR - 1 - x, WORLD_Y + BOTTOMBAR - 1 - ((hist[(today - x    ) % LHIST].drownings + hist[(today - x    ) % LHIST].collisions) * BOTTOMBAR) / maxbd, c );
+
+                  c = gdTrueColor( 255,0,255 );  // births are magenta
+                  gdImageLine( im, WORLD_X + SIDEBAR - x    , WORLD_Y + BOTTOMBAR - 1 - (hist[(today - x + 1) % LHIST].births * BOTTOMBAR) / maxbd,
+                                   WORLD_X + SIDEBAR - 1 - x, WORLD_Y + BOTTOMBAR - 1 - (hist[(today - x    ) % LHIST].births * BOTTOMBAR) / maxbd, c );
+
+               }
+            }
+
+          // Now, need to compute and plot the activity ratios down the rightbar
+            
+          for ( y = 0 ; y < WORLD_Y ; y++ )
+            { for ( r = 0 ; r < NACT ; r++ )
+                actsum[r] = 0;
+              c = 0;  // totalsum
+              for ( x = 0 ; x < WORLD_X ; x++ )
+                { if ( world[x][y].bug != NULL )
+                    { g = world[x][y].bug->birthday;
+                      b = 0;
+                      while (( g < today ) && ( b < POSHISTORY ))
+                        { actsum[ world[x][y].bug->pos[b].act ]++;
+                          b++;
+                          g++;
+                          c++;
+                        }
+                    }
+                }
+              b = g = 0;
+              if ( c > 0 )
+                { for ( r = 0 ; r < NACT ; r++ )
+                    { g += actsum[ r ];
+                      x = act_color( r );
+                      gdImageLine( im, WORLD_X + LEFTBAR + ( b * RIGHTBAR ) / c    , y,
+                                       WORLD_X + LEFTBAR + ( g * RIGHTBAR ) / c - 1, y, x );
+                      b = g;
+                    }
+                }
+            }
+
+          // Population density, age, weight down the leftbar
+          maxage = maxbugs = maxmass = maxkills = maxgenes = 1;  // Prescan for relative scaling
+	  mingenes = 1024000;
+          for ( y = 0 ; y < WORLD_Y ; y++ )
+            { age = bugs = mass = kills = genes = 0;
+              for ( x = 0 ; x < WORLD_X ; x++ )
+                { if ( world[x][y].bug != NULL )
+                    { bugs++;
+                      age   += today - world[x][y].bug->birthday;
+                      mass  += world[x][y].bug->pos[0].weight;
+                      kills += world[x][y].bug->kills;
+                      genes += world[x][y].bug->brain.ngenes;
+                    }
+                }
+              if ( bugs  < 1 ) bugs = 1;
+              age   = (  age * 1024) / bugs;
+              mass /= bugs;
+              kills = (kills * 1024) / bugs;
+              genes = (genes * 1024) / bugs;
+
+              if ( bugs  > maxbugs  ) maxbugs  = bugs ;
+              if ( age   > maxage   ) maxage   = age  ;
+              if ( mass  > maxmass  ) maxmass  = mass ;
+              if ( kills > maxkills ) maxkills = kills;
+              if ( genes > maxgenes ) maxgenes = genes;
+	      if ( genes > 0 ) { if ( genes < mingenes ) mingenes = genes; }
+            }
+          if ( mingenes >= maxgenes )
+	    { maxgenes = mingenes + 1;
+	       mingenes--;
+	    }
+          for ( y = 0 ; y < WORLD_Y ; y++ )                  // do the real plot
+            { age = bugs = mass = kills = genes = 0;
+              for ( x = 0 ; x < WORLD_X ; x++ )
+                { if ( world[x][y].bug != NULL )
+                    { bugs++;
+                      age   += today - world[x][y].bug->birthday;
+                      mass  += world[x][y].bug->pos[0].weight;
+                      kills += world[x][y].bug->kills;
+                      genes += world[x][y].bug->brain.ngenes;
+                    }
+                }
+              if ( bugs  < 1 ) bugs = 1;
+              age   = (  age * 1024) / bugs;
+              mass /= bugs;
+              kills = (kills * 1024) / bugs;
+              genes = (genes * 1024) / bugs;
+	      
+	      if ( genes == 0 ) 
+	        genes = mingenes;
+
+              if ( y > 0 )
+                { gdImageLine( im, (lastbugs  * LEFTBAR) / maxbugs , y - 1, (bugs  * LEFTBAR) / maxbugs , y, gdTrueColor( 255,255,  0 ) ); // Population in yellow
+                  gdImageLine( im, (lastage   * LEFTBAR) / maxage  , y - 1, (age   * LEFTBAR) / maxage  , y, gdTrueColor( 255,255,255 ) ); // Age in white
+                  gdImageLine( im, (lastmass  * LEFTBAR) / maxmass , y - 1, (mass  * LEFTBAR) / maxmass , y, gdTrueColor(   0,  0,255 ) ); // Mass in blue
+                  gdImageLine( im, (lastkills * LEFTBAR) / maxkills, y - 1, (kills * LEFTBAR) / maxkills, y, gdTrueColor( 255,  0,  0 ) ); // Kills in red
+                  gdImageLine( im, ((lastgenes - mingenes) * LEFTBAR) / (maxgenes - mingenes), y - 1,
+		                                 ((genes - mingenes) * LEFTBAR) / (maxgenes - mingenes), y, gdTrueColor(   0,255,  0 ) ); // Genes in green
+                }
+              lastage  = age;       
+              lastbugs = bugs;      
+              lastmass = mass;      
+              lastkills = kills;    
+              lastgenes = genes;  
+            }
+
+          if ( LEGEND )
+            { if (( fn[0] == 'b' ) || ( fn[0] == 'p' ))
+                draw_legend( im );
+               else
+                draw_env_legend( im );  // the environment map has no action colors of its own to explain
+            }
+
+          // Save the file
+   	  jpegout = fopen( fn, "wb"); //open a file
+          if ( imout != NULL )
+            { gdImageCopyResampled( imout, im, 0,0,0,0, OUT_X, OUT_Y, WORLD_X + SIDEBAR, WORLD_Y + BOTTOMBAR );
+	      save_image( imout, jpegout ); //write the resampled image
+            }
+           else
+	    save_image( im, jpegout );
+          fclose(jpegout);
+}
+
+
+//
+// Count genes with a bad type or sense index, walking the prod/sum tree like evaluate_gene
+//
+long check_genes( struct _gene *g )
+{ long bad;
+
+  if ( g == NULL )
+    return 0;
+
+  bad = 0;
+  if (( g->tp < GENECONST ) || ( g->tp > GENEMATCH )) bad++;
+  if (( g->si < 0 ) || ( g->si >= NSENSES ))         bad++;
+
+  return bad + check_genes( g->prod ) + check_genes( g->sum );
+}
+
+//
+// Collect up to max bugs from the cells x1..x2, y1..y2 inclusive, out may be NULL to only count them
+// the world wraps, so x1 > x2 (or y1 > y2) is a rectangle across the seam, corners off the map wrap onto it
+// returns the number of bugs in the rectangle, even if more than max
+//
+long bugs_in_rect( long x1, long y1, long x2, long y2, struct _bugdata **out, long max )
+{ long x,y,n;
+
+  x1 = ((x1 % WORLD_X) + WORLD_X) % WORLD_X;  // any corner wraps onto the map, or the walk below never meets x2, y2
+  y1 = ((y1 % WORLD_Y) + WORLD_Y) % WORLD_Y;
+  x2 = ((x2 % WORLD_X) + WORLD_X) % WORLD_X;
+  y2 = ((y2 % WORLD_Y) + WORLD_Y) % WORLD_Y;
+  n = 0;
+  x = x1;
+  while ( 1 )
+    { y = y1;
+      while ( 1 )
+        { if ( world[x][y].bug != NULL )
+            { if (( out != NULL ) && ( n < max ))
+                out[n] = world[x][y].bug;
+              n++;
+            }
+          if ( y == y2 ) break;
+          y = ( y + 1 ) % WORLD_Y;
+        }
+      if ( x == x2 ) break;
+      x = ( x + 1 ) % WORLD_X;
+    }
+  return n;
+}
+
+//
+// Strike with any catastrophe scheduled for today, the chance of death is drawn from limitedrandom() so runs still repeat
+//
+void catastrophe( void )
+{ struct _bugdata **list;
+  struct _catastrophe *c;
+  long x,y,i,n,x1,y1,x2,y2;
+
+  for ( c = catastrophes ; c->day > 0 ; c++ )
+    { if ( c->day != today )
+        continue;
+      x1 = ((c->x1 % WORLD_X) + WORLD_X) % WORLD_X;  // a table entry off the map wraps onto it, like bugs_in_rect()
+      y1 = ((c->y1 % WORLD_Y) + WORLD_Y) % WORLD_Y;
+      x2 = ((c->x2 % WORLD_X) + WORLD_X) % WORLD_X;
+      y2 = ((c->y2 % WORLD_Y) + WORLD_Y) % WORLD_Y;
+      printf( "Catastrophe %ld on day %ld over [%ld,%ld]-[%ld,%ld], amount %ld\n", c->kind, today, x1, y1, x2, y2, c->amount );
+      if ( c->kind == CATDIEOFF )
+        { n = bugs_in_rect( x1, y1, x2, y2, NULL, 0 );
+          if ( n == 0 )
+            continue;
+          list = (struct _bugdata **)malloc( n * sizeof( struct _bugdata * ) );
+          if ( list == NULL )
+            { printf( "Catastrophe %ld on day %ld skipped, no memory for %ld bugs\n", c->kind, today, n );
+              continue;
+            }
+          bugs_in_rect( x1, y1, x2, y2, list, n );
+          for ( i = 0 ; i < n ; i++ )
+            if ( limitedrandom( 1024 ) < c->amount )
+              kill_bug( list[i] );
+          free( list );
+          continue;
+        }
+      x = x1;
+      while ( 1 )
+        { y = y1;
+          while ( 1 )
+            { if ( c->kind == CATFAMINE ) world[x][y].food   = (long)(((long long)world[x][y].food * c->amount) / 1024);
+              if ( c->kind == CATFLOOD  ) world[x][y].water += c->amount;
+              if ( y == y2 ) break;
+              y = ( y + 1 ) % WORLD_Y;
+            }
+          if ( x == x2 ) break;
+          x = ( x + 1 ) % WORLD_X;
+        }
+    }
+}
+
+//
+// Integrity check of the whole simulation, prints each problem found and returns the number of problems
+//
+long check_world( void )
+{ struct _bugdata *bug;
+  struct _bugdata *last;
+             long  x,y,i,n,ngenes,errors;
+
+  errors = 0;
+
+  // The bug list - links, count, and each bug's own state
+  n = 0;
+  last = NULL;
+  for ( bug = buglist.first ; bug != NULL ; bug = bug->next )
+    { n++;
+      if ( bug->prev != last )
+        { printf( "selftest: bug %ld prev link broken\n", bug->brain.eth.uid ); errors++; }
+      if (( last != NULL ) && ( bug->brain.eth.uid <= last->brain.eth.uid ))  // births are appended, so the list runs in uid order
+        { printf( "selftest: bug %ld follows bug %ld in the list\n", bug->brain.eth.uid, last->brain.eth.uid ); errors++; }
+      last = bug;
+
+      if (( bug->pos[0].p.x < 0 ) || ( bug->pos[0].p.x >= WORLD_X ) ||
+          ( bug->pos[0].p.y < 0 ) || ( bug->pos[0].p.y >= WORLD_Y ))
+        { printf( "selftest: bug %ld off the map at [%ld,%ld]\n", bug->brain.eth.uid, bug->pos[0].p.x, bug->pos[0].p.y ); errors++; continue; }
+      if ( world[bug->pos[0].p.x][bug->pos[0].p.y].bug != bug )
+        { printf( "selftest: bug %ld not found in its cell [%ld,%ld]\n", bug->brain.eth.uid, bug->pos[0].p.x, bug->pos[0].p.y ); errors++; }
+      if (( bug->pos[0].face < DIR_NW ) || ( bug->pos[0].face > DIR_W ))
+        { printf( "selftest: bug %ld facing %ld\n", bug->brain.eth.uid, bug->pos[0].face ); errors++; }
+      if ( bug_alive( bug ) != ALIVE )
+        { printf( "selftest: bug %ld should have died, cause %ld\n", bug->brain.eth.uid, bug_alive( bug ) ); errors++; }
+
+      ngenes = 0;
+      for ( i = 0 ; i < NDECISIONS ; i++ )
+        { ngenes += countgenes( bug->brain.act[i].a ) + countgenes( bug->brain.act[i].b );
+          if (( bug->brain.act[i].a == NULL ) || ( bug->brain.act[i].b == NULL ))  // pruning never takes the first gene, evaluate_gene() counts on it
+            { printf( "selftest: bug %ld chromosome %ld is empty\n", bug->brain.eth.uid, i ); errors++; }
+          if ( check_genes( bug->brain.act[i].a ) + check_genes( bug->brain.act[i].b ) > 0 )
+            { printf( "selftest: bug %ld chromosome %ld has bad genes\n", bug->brain.eth.uid, i ); errors++; }
+        }
+      if ( ngenes != bug->brain.ngenes )
+        { printf( "selftest: bug %ld carries %ld genes, ngenes says %d\n", bug->brain.eth.uid, ngenes, bug->brain.ngenes ); errors++; }
+    }
+  if ( n != buglist.n_bugs )
+    { printf( "selftest: %ld bugs in the list, n_bugs says %ld\n", n, buglist.n_bugs ); errors++; }
+  if ( last != buglist.endlist )
+    { printf( "selftest: endlist is not the last bug in the list\n" ); errors++; }
+
+  // Rectangles - the whole map holds every bug, and a rectangle across the seam plus the rest of the map do too
+  if ( bugs_in_rect( 0, 0, WORLD_X - 1, WORLD_Y - 1, NULL, 0 ) != buglist.n_bugs )
+    { printf( "selftest: bugs_in_rect() misses bugs on the whole map\n" ); errors++; }
+  if ( bugs_in_rect( -WORLD_X, WORLD_Y, -1, 2 * WORLD_Y - 1, NULL, 0 ) != buglist.n_bugs )
+    { printf( "selftest: bugs_in_rect() does not wrap corners off the map\n" ); errors++; }
+  x = WORLD_X / 3;
+  y = WORLD_Y / 3;
+  if ( bugs_in_rect( WORLD_X - x, 0, x - 1, WORLD_Y - 1, NULL, 0 ) + bugs_in_rect( x, 0, WORLD_X - x - 1, WORLD_Y - 1, NULL, 0 ) != buglist.n_bugs )
+    { printf( "selftest: bugs_in_rect() across the x seam does not add up\n" ); errors++; }
+  if ( bugs_in_rect( 0, WORLD_Y - y, WORLD_X - 1, y - 1, NULL, 0 ) + bugs_in_rect( 0, y, WORLD_X - 1, WORLD_Y - y - 1, NULL, 0 ) != buglist.n_bugs )
+    { printf( "selftest: bugs_in_rect() across the y seam does not add up\n" ); errors++; }
+
+  // The world - every occupied cell must point at a bug that thinks it is there, food and water in range
+  n = 0;
+  for ( x = 0 ; x < WORLD_X ; x++ )
+    for ( y = 0 ; y < WORLD_Y ; y++ )
+      { if ( world[x][y].bug != NULL )
+          { n++;
+            if (( world[x][y].bug->pos[0].p.x != x ) || ( world[x][y].bug->pos[0].p.y != y ))
+              { printf( "selftest: cell [%ld,%ld] points at bug %ld which is at [%ld,%ld]\n", x, y, world[x][y].bug->brain.eth.uid, world[x][y].bug->pos[0].p.x, world[x][y].bug->pos[0].p.y ); errors++; }
+          }
+        if (( world[x][y].food < 0 ) || ( world[x][y].food > FOODCAP * 10 ))
+          { printf( "selftest: cell [%ld,%ld] food %ld out of range\n", x, y, world[x][y].food ); errors++; }
+        if ( world[x][y].water < 0 )
+          { printf( "selftest: cell [%ld,%ld] water %d below zero\n", x, y, world[x][y].water ); errors++; }
+      }
+  if ( n != buglist.n_bugs )
+    { printf( "selftest: %ld occupied cells for %ld bugs\n", n, buglist.n_bugs ); errors++; }
+
+  return errors;
+}
+
+//
+// The steps of a day after the dynamic challenges, run in this order by one_day()
+// grow_food() updates nearest and flows the water before it grows, rots and spreads the food
+// reorder the lines to study the effect, the shipped order is the one the reference runs use
+//
+struct _phase
+  {      char *name;
+         void (*fn)( void );
+    long long  ns;     // PROFILE: rolling average of the nanoseconds taken, the newest day weighs 1/16
+  };
+struct _phase  phases[] =
+  { { "catastrophe", catastrophe, 0 },  // scheduled disasters hit before anyone moves
+    { "immigrate",   immigrate,   0 },  // newcomers take their first turn today
+    { "move_bugs",   move_bugs,   0 },  // every bug decides and acts, ACTIONSPERDAY times
+    { "grow_food",   grow_food,   0 },  // nearest, water, then food and scent, and the day's totals
+    { NULL, NULL, 0 }
+  };
+long long  dayns;  // PROFILE: rolling average of all of one_day(), the challenges and history are the part not in phases[]
+
+long long now_ns( void )
+{ struct timespec ts;
+
+  clock_gettime( CLOCK_MONOTONIC, &ts );
+  return (long long)ts.tv_sec * 1000000000LL + ts.tv_nsec;
+}
+
+//
+// One day of the run - the dynamic challenges, then phases[], then the history is logged
+// returns 0 when the last bug has died
+//
+long one_day( void )
+{      long  i;
+  long long  t0,t1;
+
+  t0 = PROFILE ? now_ns() : 0;
+  today++;
+
+  // Dynamic challenges
+  if ( stagewait > 0 )
+    stagewait--;
+   else
+    { if (( stage == 0 ) && (buglist.n_bugs > 1000)) { foodhump = 0.9; stage = 1; stagewait =   0; }
+      if (( stage == 1 ) && (buglist.n_bugs > 3000)) { safety   = 0;   stage = 2; stagewait =   0; }
+      if (( stage == 2 ) && (buglist.n_bugs > 5000)) { leak     = 0;   stage = 3; stagewait = 250; }
+      
+      // Could use stage 3 & beyond for other things, but the forcemate escalation is somewhat population controlled using agediv
+
+//          if (( stage == 1 ) && (buglist.n_bugs > 20000)) { leak = 1; stage = 2; stagewait = 100; }
+//          if ( today == 4800 ) leak = 2;
+//          if ( today == 9600 ) leak = 3;  // progressively less leaking going on around bugs
+    }
+
+  // if ( ( today % 256 ) == 0 )
+  //   if ( today > SEASONLENGTH )
+  //     if ( geneknee2 > 100 )     // Will reach 100 around "Year 9"
+  //       geneknee2--;             // Increase the cost of carrying genes, very slowly, to evolve highly efficient bugs?
+
+  if ( today ==  3000 )    forcemate = 0x10;  // Doesn't do anything until agediv is > 0 
+  if ( today ==  4000 )    forcemate = 0x30;  // Start charging for frivilous underage divisions, and allow asexual divisions
+  if ( today ==  5000 )    forcemate = 0x70;  // Start charging more for frivilous underage divisions, and allow asexual divisions
+  if ( today ==  6000 )    forcemate = 0x71;  // Begin escalation of mating requirement
+  if ( today ==  7000 )    forcemate = 0x73;
+  if ( today ==  8000 )    forcemate = 0x77;  // Now start to force intelligent division decisions
+  if ( today ==  9000 )    forcemate = 0x7F;
+  if ( today == 10000 )     costmate =   24;   // Escalate costmate in stages
+  if ( today == 11000 )     costmate =   48;
+  if ( today == 12000 )     costmate =   96;
+  if ( today == 13000 )     costmate =  144;
+  if ( today == 14000 ) drink_or_die =    3;
+  if ( today == 15000 ) drink_or_die =    2;
+  if ( today == 16000 ) drink_or_die =    1;
+
+  if ( today > 3000 )
+    { if ( today > SEASONLENGTH )
+        { if ( ( today % 32 ) == 0 )
+            { if ( agediv < 30 )
+                foodhump = foodhump * 1.001;
+              if (( agediv > 300 ) || ( ((float)totalbug/((float)buglist.n_bugs*1024)) > MASSTARGET ))  // too long or too much weight
+                foodhump = foodhump / 1.001;
+  
+            // if ( today > SEASONLENGTH * 2 )
+            //   { target_pop = (target_pop * 2047) / 2048;
+            //     if ( target_pop < 3000 )
+            //       target_pop = 30000;  // Will result in slow increase of food supply, not instant boom
+            //   }
+
+            }
+		
+		
+          // if (( today % SEASONLENGTH ) == 0 )             
+          //   { if ( drink_or_die >= 2 )
+	      //     drink_or_die /= 2;
+		
+		  // if ( (( today / SEASONLENGTH ) % 2) == 0 )
+              //   forcemate = 0x70;  // return to allowing asexual division
+              //  else
+              //   forcemate = 0x7F;  // return to requiring sexual division
+          //   }
+
+          // if ( buglist.n_bugs < WORLD_X )
+          //   forcemate = 0x70;  // Have a heart, if heading to extinction, allow asexual division
+
+        } // endif ( today > SEASONLENGTH )
+  
+//          if ((( buglist.n_bugs * 20 ) > ( WORLD_X * WORLD_Y )) && ( agediv < 2048 ))
+      if (( buglist.n_bugs > target_pop * 2 ) && ( agediv < (today - buglist.first->birthday) ))  // Never increase beyond age of oldest bug
+        agediv++;  // up-regulate when population is greater than 5% of available space      
+
+      if ( ( today % 8 ) == 0 )
+        agediv++; // Constantly attempt to up-regulate, will be caught & rtz later when < 16384 pop
+
+    }
+
+//      if ((( buglist.n_bugs * 40 ) < ( WORLD_X * WORLD_Y )) && ( agediv > 0 ))
+  if ( (( buglist.n_bugs < target_pop ) && ( agediv > 0 )) || ( agediv > (today - buglist.first->birthday)) )
+    agediv--;  // down-regulate when population is less than 2.5% of available space      
+
+  if ( buglist.n_bugs > POP_HARDLIMIT )        // Agressive population control
+    agediv = today - buglist.first->birthday;
+
+  hist[today % LHIST].movement    =
+  hist[today % LHIST].collisions  =
+  hist[today % LHIST].starvations =
+  hist[today % LHIST].drownings   =
+  hist[today % LHIST].births      = 0;  // counters incremented in move_bugs();
+
+  for ( i = 0 ; phases[i].fn != NULL ; i++ )
+    { if ( PHASETRACE )
+        printf( "day %ld phase %ld %s\n", today, i, phases[i].name );
+      t1 = PROFILE ? now_ns() : 0;
+      phases[i].fn();
+      if ( PROFILE )
+        phases[i].ns += ( now_ns() - t1 - phases[i].ns ) / 16;
+    }
+
+#if DEBUGINDEX
+  i = check_world();
+  if ( i > 0 )
+    { printf( "DEBUGINDEX: %ld problems after day %ld\n", i, today );
+      abort();
+    }
+#endif
+
+  if ( buglist.n_bugs == 0 )
+    return 0;
+
+  hist[today % LHIST].n_bugs    = buglist.n_bugs;
+  hist[today % LHIST].avgweight = totalbug / buglist.n_bugs;
+  hist[today % LHIST].avgfood   = (totalfood * 1024)/(WORLD_X * WORLD_Y);
+  hist[today % LHIST].avggenes  = (genecount * 1024)/buglist.n_bugs;
+  for ( i = 0 ; i < ETHBANDS ; i++ )
+    hist[today % LHIST].ethnic[i] = ethcount[i];
+  if ( PROFILE )
+    dayns += ( now_ns() - t0 - dayns ) / 16;
+  return 1;
+}
+
+//
+// Has the run gone flat - bugs, food, movement and births all within 1/32 of their high over the last STUCKWINDOW days
+// nothing is changing, so nothing is evolving either
+//
+long run_stuck( void )
+{ long d,i,v,lo[4],hi[4];
+
+  if (( STUCKWINDOW <= 0 ) || ( today < STUCKWINDOW ))
+    return 0;
+  for ( d = today - STUCKWINDOW + 1 ; d <= today ; d++ )
+    for ( i = 0 ; i < 4 ; i++ )
+      { switch ( i )
+          { case 0:  v = hist[d % LHIST].n_bugs;   break;
+            case 1:  v = hist[d % LHIST].avgfood;  break;
+            case 2:  v = hist[d % LHIST].movement; break;
+            default: v = hist[d % LHIST].births;   break;
+          }
+        if (( d == today - STUCKWINDOW + 1 ) || ( v < lo[i] )) lo[i] = v;
+        if (( d == today - STUCKWINDOW + 1 ) || ( v > hi[i] )) hi[i] = v;
+      }
+  for ( i = 0 ; i < 4 ; i++ )
+    if ( ( hi[i] - lo[i] ) * 32 > hi[i] )
+      return 0;
+  return 1;
+}
+
+//
+// One line on the console about the state of the run
+//
+void status_line( long day )
+{ printf( "%6ldDy %5ldBg %4.1f%% %10ld %10ld F=%5.0f B=%5.0f Gns=%6.2f AD%4ld FH%6.3f FM%02x\n", day, buglist.n_bugs, ((float)(buglist.n_bugs * 100))/((float)(WORLD_X * WORLD_Y)), buglist.first->brain.eth.uid, idcounter, (float)totalfood/((float)WORLD_X * WORLD_Y), (float)totalbug/((float)buglist.n_bugs*1024),((float)genecount)/((float)buglist.n_bugs),agediv,foodhump,forcemate ); 
+  if ( PROFILE )
+    { long i;
+
+      printf( "        day %8.3fms", dayns / 1e6 );
+      for ( i = 0 ; phases[i].fn != NULL ; i++ )
+        printf( ", %s %8.3fms", phases[i].name, phases[i].ns / 1e6 );
+      printf( "\n" );
+    }
+}
+
+//
+// Ctrl-C only raises a flag, main() and advance() stop at the end of the day so the final report sees a whole day
+//
+volatile sig_atomic_t stopflag = 0;
+
+void on_interrupt( int sig )
+{ (void)sig;
+  stopflag = 1;
+}
+
+//
+// Run up to n days, stopping early if the bugs die out, on Ctrl-C or when STUCKSTOP finds the run stuck, progress() is told the day every 100 days
+// returns the number of days actually run
+//
+long advance( long n, void (*progress)( long ) )
+{ long i;
+
+  for ( i = 0 ; i < n ; i++ )
+    { if ( !one_day() )
+        return i + 1;
+      if (( progress != NULL ) && ( today % 100 == 0 ))
+        progress( today );
+      if (( STUCKWINDOW > 0 ) && ( stuckday == 0 ) && ( today % 64 == 0 ) && run_stuck() )
+        { stuckday = today;
+          printf( "Stuck - nothing has changed for %d days, on day %ld.\n", STUCKWINDOW, today );
+          if ( STUCKSTOP )  // same as an interrupt, the caller writes its final outputs
+            return i + 1;
+        }
+      if ( stopflag )  // the caller still writes its final outputs
+        { printf( "Interrupted on day %ld.\n", today );
+          return i + 1;
+        }
+    }
+  return n;
+}
+
+//
+// FNV-1a over every cell and every bug in list order, two runs match only if they are the same run
+//
+unsigned long long state_hash( void )
+{ unsigned long long h;
+  struct _bugdata *bp;
+              long  x,y,v[6],i;
+
+  h = 14695981039346656037ULL;
+  for ( x = 0 ; x < WORLD_X ; x++ )
+    for ( y = 0 ; y < WORLD_Y ; y++ )
+      { v[0] = world[x][y].food;
+        v[1] = world[x][y].water;
+        v[2] = world[x][y].scent;
+        for ( i = 0 ; i < 3 ; i++ )
+          { h ^= (unsigned long long)v[i];
+            h *= 1099511628211ULL;
+          }
+      }
+  for ( bp = buglist.first ; bp != NULL ; bp = bp->next )
+    { v[0] = bp->brain.eth.uid;
+      v[1] = bp->pos[0].p.x;
+      v[2] = bp->pos[0].p.y;
+      v[3] = bp->pos[0].weight;
+      v[4] = bp->pos[0].hydrate;
+      v[5] = bp->brain.ngenes;
+      for ( i = 0 ; i < 6 ; i++ )
+        { h ^= (unsigned long long)v[i];
+          h *= 1099511628211ULL;
+        }
+    }
+  h ^= (unsigned long long)rngdraws;
+  h *= 1099511628211ULL;
+  return h;
+}
+
+//
+// Rerun each of vectors[] and compare, a mismatch with the shipped defines means this build
+// does not reproduce the reference runs.  All of the sim is integer math except growing_season(),
+// which takes sin() and cos() in float, so a different libm or -ffast-math can move a result by one
+// and the runs drift apart from there.
+//
+int verify_vectors( void )
+{ struct _vector *v;
+  unsigned long long h;
+              long  fails = 0;
+
+  for ( v = vectors ; v->days != 0 ; v++ )
+    { reset_sim( v->seed );
+      advance( v->days, NULL );
+      h = state_hash();
+      printf( "verify: seed %ld, %ld days, %ld bugs, hash %016llx", v->seed, v->days, buglist.n_bugs, h );
+      if (( buglist.n_bugs == v->n_bugs ) && ( h == v->hash ))
+        printf( " ok\n" );
+       else
+        { printf( " MISMATCH, expected %ld bugs, hash %016llx\n", v->n_bugs, v->hash );
+          fails++;
+        }
+    }
+  if ( fails > 0 )
+    { printf( "verify: FAILED, %ld of the vectors differ\n", fails );
+      return 1;
+    }
+  printf( "verify: passed\n" );
+  return 0;
+}
+
+//
+// Run a short simulation from bug one, checking integrity every day
+// then run it twice more from RNGSEED, both must end on the same state_hash()
+//
+int self_test( void )
+{ long errors,f,d,n,first,days,bugs,draws;
+  long col[LHIST];
+  unsigned long long h;
+  struct _bugdata *bug;
+
+  init_world();
+  bug_one();
+  errors = check_world();
+
+  while (( today < SELFTESTDAYS ) && ( buglist.n_bugs > 0 ) && ( errors == 0 ))
+    { one_day();
+      errors += check_world();
+    }
+
+  // FREEZEGENES, every bug must still carry the brain of bug one
+  if ( FREEZEGENES && ( IMMIGRATE == 0 ) )
+    for ( bug = buglist.first ; bug != NULL ; bug = bug->next )
+      if (( genome_distance( &(bug->brain), &(buglist.first->brain) ) != 0 ) || ( bug->brain.expression != buglist.first->brain.expression ) || ( bug->brain.divide != buglist.first->brain.divide ))
+        { printf( "selftest: FREEZEGENES bug %ld is not a clone of bug %ld\n", bug->brain.eth.uid, buglist.first->brain.eth.uid ); errors++; }
+
+  // stats.bin round trip, every column must read back as written
+  write_stats_bin( "selftest.bin" );
+  first = ( today >= LHIST ) ? today - LHIST + 1 : 1;
+  for ( f = 0 ; ( f < NSTATFIELDS ) && ( buglist.n_bugs > 0 ) ; f++ )
+    { n = read_stats_bin( "selftest.bin", f, col, LHIST );
+      if ( n != today - first + 1 )
+        { printf( "selftest: stats.bin column %s read %ld days, expected %ld\n", statname[f], n, today - first + 1 ); errors++; continue; }
+      for ( d = 0 ; d < n ; d++ )
+        if ( col[d] != stat_field( first + d, f ) )
+          { printf( "selftest: stats.bin column %s day %ld reads %ld, expected %ld\n", statname[f], first + d, col[d], stat_field( first + d, f ) ); errors++; break; }
+    }
+  remove( "selftest.bin" );
+
+  if ( errors > 0 )
+    { printf( "selftest: FAILED on day %ld, %ld problems\n", today, errors );
+      return 1;
+    }
+  days  = today;
+  bugs  = buglist.n_bugs;
+  draws = rngdraws;
+
+  // determinism, two runs from the same seed must end in the same state
+  reset_sim( RNGSEED );
+  advance( SELFTESTDAYS, NULL );
+  h = state_hash();
+  reset_sim( RNGSEED );
+  advance( SELFTESTDAYS, NULL );
+  if ( state_hash() != h )
+    { printf( "selftest: FAILED, two runs from seed %d differ by day %ld\n", RNGSEED, today );
+      return 1;
+    }
+  printf( "selftest: passed, %ld days, %ld bugs, %ld random draws\n", days, bugs, draws );
+  return 0;
+}
+
+
+int main( int argc, char **argv )
+{       long  done,interval;
+        long lastbugcount = 0;
+  gdImagePtr  im,imout; //declaration of the image
+        char  fn[20],in[20];
+        long  restarts = 0;
+
+  rot[0] =  988; // .966 @ 10, .342 @ 30  Go easy on the bug itself
+  rot[1] =  973; //                       Heavier rot next door...
+  rot[2] = 1012; //                       Very light farther on
+  rot[3] = 1023; //                       Just a bit less than stopgrowth out here
+
+  if (( argc > 1 ) && ( strcmp( argv[1], "selftest" ) == 0 ))
+    return self_test();
+
+  if (( argc > 1 ) && ( strcmp( argv[1], "verify" ) == 0 ))
+    return verify_vectors();
+
+  im    = gdImageCreateTrueColor(WORLD_X + SIDEBAR,WORLD_Y + BOTTOMBAR); //create an image
+  imout = NULL;
+  if (( OUT_X > 0 ) && ( OUT_Y > 0 ))
+    imout = gdImageCreateTrueColor( OUT_X, OUT_Y ); //create a resized output image
+
+  // for ( interval = 2 ; interval < 8 ; interval *= 2 )
+  //   for ( done = 0 ; done < 64; done++ )
+  //     printf("%ld - %ld\n",interval, limitedrandom( interval ) );
+
+  init_world();
+  load_seed_image( SEEDIMAGE );
+  load_scenario( SCENARIO );
+  bug_one();  // Load the original bug
+  signal( SIGINT, on_interrupt );
+  write_run_info( "run.txt" );
+
+  if (( argc > 2 ) && ( strcmp( argv[1], "dot" ) == 0 ))  // fast forward, then the oldest bug's brain as Graphviz
+    { FILE *fp;
+
+      done = advance( atol( argv[2] ), status_line );
+      printf( "%ld days run, %ld bugs\n", done, buglist.n_bugs );
+      if (( fp = fopen( "bug.dot", "w" ) ) != NULL )
+        { brain_dot( fp, buglist.first );
+          fclose( fp );
+        }
+      gdImageDestroy(im);
+      if ( imout != NULL )
+        gdImageDestroy(imout);
+      return 0;
+    }
+
+  if (( argc > 2 ) && ( strcmp( argv[1], "days" ) == 0 ))  // fast forward, no plots along the way
+    { done = advance( atol( argv[2] ), status_line );
+      printf( "%ld days run, %ld bugs\n", done, buglist.n_bugs );
+      write_stats( "stats.csv" );
+      if ( STATSBIN )
+        write_stats_bin( "stats.bin" );
+      if ( buglist.n_bugs > 0 )
+        { bug_report( "final.html", "final" IMGEXT );
+          image_plot( im, imout, "final" IMGEXT );
+        }
+      gdImageDestroy(im);
+      if ( imout != NULL )
+        gdImageDestroy(imout);
+      return 0;
+    }
+
+  interval = FRAMEINTERVAL;
+  done = 0;
+  while ( !done )
+    { 
+//      if ( today ==   8192 ) interval =   32;  // 16 sec
+//      if ( today ==   9216 ) interval =   64;  // 1 sec
+//      if ( today ==  11264 ) interval =  128;  // 1 sec
+//      if ( today ==  15360 ) interval =  256;  // 1 sec
+//      if ( today ==  23552 ) interval =  512;  // 1 sec
+//      if ( today ==  39936 ) interval = 1024;  // 3 sec
+//      if ( today == 131072 ) interval =  512;  // .5 sec
+//      if ( today == 139264 ) interval =  256;  // .5 sec
+//      if ( today == 143360 ) interval =  128;  // 5 sec
+//      if ( today == 163840 ) interval =   64;  // .5 sec
+//      if ( today == 164864 ) interval =   24;  // .5 sec
+//      if ( today == 165376 ) interval =    8;  // 16 sec
+//      if ( today == 173568 ) interval =   32;  // 1 sec
+//      if ( today == 174592 ) interval =   64;  // 1 sec
+//      if ( today == 176640 ) interval =  128;  // 1 sec
+//      if ( today == 180736 ) interval =  256;  
+//      if ( today == 262144 ) interval =  512;  // Begin year 8
+//      if ( today == 294912 ) interval = 1024;  // Begin year 9
+
+      if ( !one_day() )
+        { if ( restarts < RESTARTS )
+            { restarts++;
+              printf("All bugs dead, restart %ld with seed %ld.\n", restarts, RNGSEED + restarts );
+              reset_sim( RNGSEED + restarts );
+              lastbugcount = 0;
+              continue;
+            }
+          done = 1;
+          printf("All bugs dead.\n");
+        }
+       else       
+        { if (( today % 100 == 0 ) || ((lastbugcount < 100) && (buglist.n_bugs != lastbugcount))) // in shell update
+            { status_line( today );
+	      lastbugcount = buglist.n_bugs;
+	    }
+	    
+        }
+
+      if ( RECORDFRAMES && ( today % (SEASONLENGTH / 8) == 0 ))
+        { sprintf( fn, "year%02ld%02ld.html", today / SEASONLENGTH, (today % SEASONLENGTH) / 1024 );
+          sprintf( in, "year%02ld%02ld" IMGEXT,  today / SEASONLENGTH, (today % SEASONLENGTH) / 1024 );
+          bug_report( fn, in );  // Archival reports
+          image_plot( im, imout, in );
+        }
+          
+      // if ( today % 1000 == 0 )
+      //   { bug_report( "newreport.html", "bugs.jpg" );  // Periodic reports for web update
+      //     image_plot( im, "newbugs.jpg" );             // renamed to bugs.jpg by the script
+      //   }
+          
+      if ( RECORDFRAMES && ( today % interval == 0 ))
+        { sprintf( in, "b%07ld" IMGEXT, today );  // images for later animation
+          image_plot( im, imout, in );                  
+	  sprintf( in, "e%07ld" IMGEXT, today );  // images for later animation
+          image_plot( im, imout, in );                  
+          if ( PHENOMAP )
+            { sprintf( in, "p%07ld" IMGEXT, today );
+              image_plot( im, imout, in );
+            }
+        }
+
+      if (( STUCKWINDOW > 0 ) && !done && ( stuckday == 0 ) && ( today % 64 == 0 ) && run_stuck() )
+        { stuckday = today;
+          printf("Stuck - nothing has changed for %d days, on day %ld.\n", STUCKWINDOW, today );
+          if ( STUCKSTOP )
+            { done = 1;
+              bug_report( "final.html", "final" IMGEXT );
+              image_plot( im, imout, "final" IMGEXT );
+            }
+        }
+
+      if ( stopflag && !done )
+        { done = 1;
+          printf("Interrupted on day %ld.\n", today );
+          bug_report( "final.html", "final" IMGEXT );
+          image_plot( im, imout, "final" IMGEXT );
+        }
+ 
+    }  
+  write_stats( "stats.csv" );
+  if ( STATSBIN )
+    write_stats_bin( "stats.bin" );
+  
+   	//  jpegout = fopen( fn, "w"); //open a file
+        //  gdImageCopyResampled( imout, im, 0,0,0,0, OUT_X, OUT_Y, PLOT_X, PLOT_Y);
+	//  gdImageJpeg(imout, jpegout, -1); //write the image to the file using the default quality setting
+        //  printf("%s %7.4f<x<%7.4f %7.4f<y<%7.4f\n",fn,minx,maxx,miny,maxy);
+        //  fclose(jpegout); 
+
+       
+
+	/* be good, clean up stuff */
+	gdImageDestroy(im);
+	if ( imout != NULL )
+	  gdImageDestroy(imout);
+}
+